@@ -0,0 +1,53 @@
+//! Compares linear scan over stations with global event queue.
+//! Sleep logic is disabled, because its threshold checks visit every station after each event.
+//!
+//! Run with `cargo bench --bench scheduler`
+
+use std::time::Instant;
+
+use cassidy::{Config, SchedulerKind, SimulationBuilder};
+
+// approximate number of processed events in single run
+const EVENTS: f64 = 500_000.0;
+
+fn main() {
+    println!("stations | scan [ms] | queue [ms] | speedup");
+    println!("---------+-----------+------------+--------");
+    for stations_count in [10, 100, 1000] {
+        let cfg = Config {
+            stations_count,
+            lambda: 2.0,
+            ..Default::default()
+        };
+        // keep number of events similar for every stations count
+        // (arrival and release per user, half of lambda in first phase)
+        let duration = EVENTS / (2.0 * cfg.lambda * 0.5 * stations_count as f64) / 3600.0;
+
+        let mut times = Vec::new();
+        let mut reports = Vec::new();
+        for kind in [SchedulerKind::Scan, SchedulerKind::Queue] {
+            let scene = SimulationBuilder::new(cfg.clone())
+                .duration(duration)
+                .seed(1)
+                .scheduler(kind)
+                .build()
+                .unwrap();
+            let start = Instant::now();
+            let res = scene.run(0).unwrap();
+            times.push(start.elapsed().as_secs_f64() * 1000.0);
+            reports.push(res.get_csv());
+        }
+        assert_eq!(
+            reports[0], reports[1],
+            "Schedulers gave different results for {} stations",
+            stations_count
+        );
+        println!(
+            "{:^8} | {:^9.1} | {:^10.1} | {:^6.1}",
+            stations_count,
+            times[0],
+            times[1],
+            times[0] / times[1]
+        );
+    }
+}
@@ -0,0 +1,302 @@
+use std::fmt::Debug;
+
+use rand::{rngs::StdRng, Rng};
+use rand_distr::{Distribution, Exp, Pareto};
+
+use crate::config::ArrivalProcessKind;
+
+/// Generator of inter-arrival times of new users in single station
+pub trait ArrivalProcess: Debug {
+    /// Returns time [us] from `time` to the next arrival,
+    /// for given current arrival rate `lambda` [users per second].
+    fn next_arrival(&mut self, time: u64, lambda: f64, rng: &mut StdRng) -> u64;
+}
+
+pub fn create_process(kind: &ArrivalProcessKind) -> Box<dyn ArrivalProcess> {
+    match kind {
+        ArrivalProcessKind::Poisson => Box::new(PoissonProcess),
+        ArrivalProcessKind::Mmpp { states } => {
+            // fraction of time spent in each state is proportional to its mean time
+            let total_time: f64 = states.iter().map(|s| s.mean_time).sum();
+            let mean_rate = states.iter().map(|s| s.rate * s.mean_time).sum::<f64>() / total_time;
+            Box::new(MmppProcess {
+                rates: states.iter().map(|s| s.rate / mean_rate).collect(),
+                mean_times: states.iter().map(|s| s.mean_time * 1000.0).collect(),
+                state: 0,
+                switch_at: None,
+            })
+        }
+        ArrivalProcessKind::Batch { min_size, max_size } => Box::new(BatchProcess {
+            min_size: *min_size,
+            max_size: *max_size,
+            remaining: 0,
+        }),
+        ArrivalProcessKind::Deterministic => Box::new(DeterministicProcess),
+        ArrivalProcessKind::Pareto { shape } => Box::new(ParetoProcess { shape: *shape }),
+    }
+}
+
+/// Returns timestamp of the first arrival after `time` of process with time-varying rate.
+/// Candidate arrivals are generated with `max_rate` and accepted with probability equal to
+/// ratio of `rate` at candidate timestamp and `max_rate` (thinning).
+pub fn thinned_arrival(
+    process: &mut dyn ArrivalProcess,
+    time: u64,
+    max_rate: f64,
+    rate: impl Fn(u64) -> f64,
+    rng: &mut StdRng,
+) -> u64 {
+    let mut candidate = time;
+    loop {
+        candidate = candidate.saturating_add(process.next_arrival(candidate, max_rate, rng));
+        if candidate == u64::MAX {
+            return candidate;
+        }
+        if rng.gen::<f64>() * max_rate < rate(candidate) {
+            return candidate;
+        }
+    }
+}
+
+/// Returns exponentially distributed time [us] with given rate [1/s], u64::MAX for zero rate
+fn exponential(rate: f64, rng: &mut StdRng) -> u64 {
+    if rate == 0.0 {
+        return u64::MAX;
+    }
+    // converted from seconds to microseconds
+    (Exp::new(rate).unwrap().sample(rng) * 1_000_000.0) as u64
+}
+
+#[derive(Debug)]
+pub struct PoissonProcess;
+
+impl ArrivalProcess for PoissonProcess {
+    fn next_arrival(&mut self, _time: u64, lambda: f64, rng: &mut StdRng) -> u64 {
+        exponential(lambda, rng)
+    }
+}
+
+#[derive(Debug)]
+pub struct MmppProcess {
+    rates: Vec<f64>,      // multipliers of lambda
+    mean_times: Vec<f64>, // [us]
+    state: usize,
+    switch_at: Option<u64>,
+}
+
+impl MmppProcess {
+    fn sojourn(&self, rng: &mut StdRng) -> u64 {
+        Exp::new(1.0 / self.mean_times[self.state])
+            .unwrap()
+            .sample(rng) as u64
+    }
+}
+
+impl ArrivalProcess for MmppProcess {
+    fn next_arrival(&mut self, time: u64, lambda: f64, rng: &mut StdRng) -> u64 {
+        if lambda == 0.0 {
+            return u64::MAX;
+        }
+        let mut now = time;
+        let mut switch_at = match self.switch_at {
+            Some(t) => t,
+            None => time.saturating_add(self.sojourn(rng)),
+        };
+        loop {
+            // exponential distribution is memoryless, so arrival can be drawn again after each switch
+            let arrival = now.saturating_add(exponential(lambda * self.rates[self.state], rng));
+            if arrival < switch_at {
+                self.switch_at = Some(switch_at);
+                return arrival - time;
+            }
+            // switches are not generated after the end of time, so there will be no arrival
+            if switch_at == u64::MAX {
+                self.switch_at = Some(switch_at);
+                return u64::MAX;
+            }
+            now = switch_at;
+            let next = rng.gen_range(0..self.rates.len() - 1);
+            self.state = if next >= self.state { next + 1 } else { next };
+            switch_at = now.saturating_add(self.sojourn(rng));
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BatchProcess {
+    min_size: usize,
+    max_size: usize,
+    remaining: usize, // users of current batch, which did not arrive yet
+}
+
+impl ArrivalProcess for BatchProcess {
+    fn next_arrival(&mut self, _time: u64, lambda: f64, rng: &mut StdRng) -> u64 {
+        if self.remaining > 0 {
+            self.remaining -= 1;
+            return 0;
+        }
+        self.remaining = rng.gen_range(self.min_size..=self.max_size) - 1;
+        let mean_size = (self.min_size + self.max_size) as f64 / 2.0;
+        exponential(lambda / mean_size, rng)
+    }
+}
+
+#[derive(Debug)]
+pub struct DeterministicProcess;
+
+impl ArrivalProcess for DeterministicProcess {
+    fn next_arrival(&mut self, _time: u64, lambda: f64, _rng: &mut StdRng) -> u64 {
+        (1_000_000.0 / lambda) as u64
+    }
+}
+
+#[derive(Debug)]
+pub struct ParetoProcess {
+    shape: f64,
+}
+
+impl ArrivalProcess for ParetoProcess {
+    fn next_arrival(&mut self, _time: u64, lambda: f64, rng: &mut StdRng) -> u64 {
+        // scale is chosen so that mean inter-arrival time equals 1 / lambda
+        let scale = (self.shape - 1.0) / (self.shape * lambda);
+        (Pareto::new(scale, self.shape).unwrap().sample(rng) * 1_000_000.0) as u64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::create_process;
+    use crate::config::{ArrivalProcessKind, MmppState};
+    use rand::{rngs::StdRng, SeedableRng};
+    use rand_distr::{Distribution, Exp};
+
+    /// Returns mean rate [1/s] and index of dispersion of counts in 1 s windows
+    fn arrival_stats(kind: &ArrivalProcessKind, lambda: f64, count: usize) -> (f64, f64) {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut process = create_process(kind);
+        let mut time = 0;
+        let mut windows = Vec::new();
+        for _ in 0..count {
+            time += process.next_arrival(time, lambda, &mut rng);
+            let window = (time / 1_000_000) as usize;
+            if window >= windows.len() {
+                windows.resize(window + 1, 0.0);
+            }
+            windows[window] += 1.0;
+        }
+        let n = windows.len() as f64;
+        let mean = windows.iter().sum::<f64>() / n;
+        let variance = windows.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+        (count as f64 / (time as f64 / 1_000_000.0), variance / mean)
+    }
+
+    #[test]
+    fn poisson() {
+        // must keep random sequence of original implementation
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut reference = StdRng::seed_from_u64(1);
+        let mut process = create_process(&ArrivalProcessKind::Poisson);
+        for _ in 0..100 {
+            let expected = (Exp::new(5.0).unwrap().sample(&mut reference) * 1_000_000.0) as u64;
+            assert_eq!(process.next_arrival(0, 5.0, &mut rng), expected);
+        }
+        let (rate, dispersion) = arrival_stats(&ArrivalProcessKind::Poisson, 10.0, 100_000);
+        assert!((rate - 10.0).abs() < 0.2, "rate: {}", rate);
+        assert!((dispersion - 1.0).abs() < 0.1, "dispersion: {}", dispersion);
+    }
+
+    #[test]
+    fn processes() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut process = create_process(&ArrivalProcessKind::Deterministic);
+        assert_eq!(process.next_arrival(0, 4.0, &mut rng), 250_000);
+        let (rate, dispersion) = arrival_stats(&ArrivalProcessKind::Deterministic, 10.0, 10_000);
+        assert!((rate - 10.0).abs() < 0.01, "rate: {}", rate);
+        assert!(dispersion < 0.01, "dispersion: {}", dispersion);
+
+        let batch = ArrivalProcessKind::Batch {
+            min_size: 3,
+            max_size: 3,
+        };
+        let mut process = create_process(&batch);
+        let delays: Vec<u64> = (0..6)
+            .map(|_| process.next_arrival(0, 1.0, &mut rng))
+            .collect();
+        assert!(delays[0] > 0 && delays[3] > 0);
+        assert_eq!([delays[1], delays[2], delays[4], delays[5]], [0; 4]);
+
+        // bursty processes keep mean rate, but have higher variance of counts than Poisson
+        let mmpp = ArrivalProcessKind::Mmpp {
+            states: vec![
+                MmppState {
+                    rate: 0.2,
+                    mean_time: 20_000.0,
+                },
+                MmppState {
+                    rate: 5.0,
+                    mean_time: 5_000.0,
+                },
+            ],
+        };
+        let batch = ArrivalProcessKind::Batch {
+            min_size: 1,
+            max_size: 9,
+        };
+        for (kind, rate_tolerance) in [(mmpp, 0.5), (batch, 0.3)] {
+            let (rate, dispersion) = arrival_stats(&kind, 10.0, 200_000);
+            assert!(
+                (rate - 10.0).abs() < rate_tolerance,
+                "{:?} rate: {}",
+                kind,
+                rate
+            );
+            assert!(dispersion > 2.0, "{:?} dispersion: {}", kind, dispersion);
+        }
+        // heavy tail makes mean converge slowly
+        let pareto = ArrivalProcessKind::Pareto { shape: 2.5 };
+        let (rate, _) = arrival_stats(&pareto, 10.0, 200_000);
+        assert!((rate - 10.0).abs() < 0.5, "rate: {}", rate);
+    }
+
+    #[test]
+    fn zero_rate() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mmpp = ArrivalProcessKind::Mmpp {
+            states: vec![
+                MmppState {
+                    rate: 0.0,
+                    mean_time: 1000.0,
+                },
+                MmppState {
+                    rate: 1.0,
+                    mean_time: 1000.0,
+                },
+            ],
+        };
+        for kind in [
+            ArrivalProcessKind::Poisson,
+            mmpp.clone(),
+            ArrivalProcessKind::Batch {
+                min_size: 1,
+                max_size: 3,
+            },
+            ArrivalProcessKind::Deterministic,
+            ArrivalProcessKind::Pareto { shape: 2.5 },
+        ] {
+            let mut process = create_process(&kind);
+            assert_eq!(
+                process.next_arrival(0, 0.0, &mut rng),
+                u64::MAX,
+                "{:?}",
+                kind
+            );
+        }
+        // state without arrivals is left after its sojourn time
+        let mut process = create_process(&mmpp);
+        let mut time = 0;
+        for _ in 0..100 {
+            time += process.next_arrival(time, 1.0, &mut rng);
+        }
+        assert!(time < 1_000_000_000, "time: {}", time);
+    }
+}
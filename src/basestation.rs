@@ -1,14 +1,21 @@
-use std::cmp::Ordering;
+use std::{cmp::Ordering, collections::VecDeque};
 
-use crate::{config::Config, logger::Logger, sim_container::SimState, user::User};
+use crate::{
+    arrival::{create_process, thinned_arrival, ArrivalProcess},
+    config::{Config, LambdaProfile, StationParams},
+    error::CassidyError,
+    logger::Logger,
+    sim_container::SimState,
+    trace::TraceRecord,
+    user::User,
+};
 use binary_heap_plus::{BinaryHeap, FnComparator};
 use rand::rngs::StdRng;
-use rand_distr::Distribution;
 
 #[derive(Debug)]
 pub enum BaseStationState {
     Active,
-    Sleep,
+    Sleep(usize),   // Station is sleeping in given sleep level
     PowerUp(u64),   // Station is during power-up process
     PowerDown(u64), // Station is during power-down process
 }
@@ -26,38 +33,119 @@ pub struct BaseStationResult {
     pub average_power: f64,
     pub average_usage: f64,
     pub average_sleep_time: f64,
+    pub sleep_level_times: Vec<f64>, // share of time spent in every sleep level
+    pub energy: f64,                 // [Wh]
+}
+
+/// Transition between sleep and active mode, in progress or the last one
+#[derive(Debug, Clone, Copy, Default)]
+struct Transition {
+    start: u64,         // [us]
+    duration: u64,      // [us]
+    powers: (f64, f64), // [W] of the left and the entered state
+}
+
+type UsersHeap = BinaryHeap<User, FnComparator<fn(&User, &User) -> Ordering>>;
+
+/// One hour [us]
+const HOUR: u64 = 3_600_000_000;
+
+/// Number of hourly energy counters, 0 when energy is not priced
+fn hourly_len(cfg: &Config) -> usize {
+    if cfg.tariff.is_some() || cfg.carbon_intensity.is_some() {
+        24
+    } else {
+        0
+    }
 }
 
 #[derive(Debug)]
 pub struct BaseStation {
     pub id: usize,
-    resources: BinaryHeap<User, FnComparator<fn(&User, &User) -> Ordering>>,
+    pub params: StationParams,
+    resources: UsersHeap,
+    used_blocks: usize,
+    arrival: Box<dyn ArrivalProcess>,
+    trace: Option<VecDeque<TraceRecord>>,
     pub next_user_add: u64,
     pub state: BaseStationState,
     pub total_power: f64,
     pub total_usage: f64,
     pub sleep_time: u64,
+    pub sleep_level_times: Vec<u64>,
+    /// Time of entering current sleep level
+    pub sleep_since: u64,
+    transition: Transition,
+    /// Energy [W * us] consumed in each hour of the day and energy the station would consume
+    /// if it was always on, with usage it had at the time. Users offloaded from sleeping station
+    /// are charged to the baseline of station serving them.
+    /// Tracked only when tariff or carbon intensity is configured
+    hourly_energy: Vec<f64>,
+    hourly_baseline: Vec<f64>,
+    last_update: u64,
 }
 
 impl BaseStation {
     pub fn new(id: usize, cfg: &Config, lambda: f64, rng: &mut StdRng) -> BaseStation {
-        BaseStation {
+        let params = cfg.station_params(id);
+        let arrival = create_process(&params.arrival_process);
+        let mut station = BaseStation {
             id,
-            resources: BinaryHeap::with_capacity_by(cfg.resources_count, |a: &User, b: &User| {
-                b.end.partial_cmp(&a.end).unwrap()
-            }),
-            next_user_add: BaseStation::get_new_timestamp(lambda, rng),
+            resources: BinaryHeap::with_capacity_by(
+                params.resources_count,
+                |a: &User, b: &User| b.end.partial_cmp(&a.end).unwrap(),
+            ),
+            used_blocks: 0,
+            next_user_add: 0,
+            arrival,
+            trace: None,
+            params,
             state: BaseStationState::Active,
             total_power: 0.0,
             total_usage: 0.0,
             sleep_time: 0,
+            sleep_level_times: vec![0; cfg.station_params(id).sleep_levels.len()],
+            sleep_since: 0,
+            transition: Transition::default(),
+            hourly_energy: vec![0.0; hourly_len(cfg)],
+            hourly_baseline: vec![0.0; hourly_len(cfg)],
+            last_update: 0,
+        };
+        station.next_user_add = station.next_arrival(0, lambda, cfg, rng);
+        station
+    }
+
+    /// Returns timestamp of the first arrival after given time.
+    /// Given lambda of global step profile is used, if station follows that profile without offset.
+    /// Other profiles are handled by thinning: candidate arrivals are generated with maximal rate
+    /// and accepted with probability equal to ratio of current and maximal rate.
+    fn next_arrival(&mut self, time: u64, lambda: f64, cfg: &Config, rng: &mut StdRng) -> u64 {
+        let weight = self.params.lambda_weight;
+        let profile = &self.params.lambda_profile;
+        if cfg.lambda_profile == LambdaProfile::Step
+            && *profile == LambdaProfile::Step
+            && self.params.phase_offset == 0.0
+        {
+            return time.saturating_add(self.arrival.next_arrival(time, lambda * weight, rng));
+        }
+        let max_lambda = cfg.lambda * weight * profile.max_coef(&cfg.lambda_coefs);
+        let params = &self.params;
+        let lambda = |t: u64| params.lambda_at(t, cfg);
+        thinned_arrival(self.arrival.as_mut(), time, max_lambda, lambda, rng)
+    }
+
+    /// Schedules the next arrival after change of lambda of global step profile,
+    /// if the station was left without arrivals by zero lambda
+    pub fn resume_arrivals(&mut self, time: u64, lambda: f64, cfg: &Config, rng: &mut StdRng) {
+        if self.trace.is_none() && self.next_user_add == u64::MAX {
+            self.next_user_add = self.next_arrival(time, lambda, cfg, rng);
         }
     }
 
-    /// Returns random timestamp based on exponetial distribution.
-    fn get_new_timestamp(lambda: f64, rng: &mut StdRng) -> u64 {
-        // converted from seconds to microseconds
-        (rand_distr::Exp::new(lambda).unwrap().sample(rng) * 1000_000.0) as u64
+    /// Replaces randomly generated users with given recorded arrivals, sorted by time
+    pub fn replay(&mut self, records: VecDeque<TraceRecord>) {
+        self.next_user_add = records.front().map_or(u64::MAX, |r| r.time);
+        self.trace = Some(records);
     }
 
     pub fn get_next_event(&self) -> (u64, BaseStationEvent) {
@@ -77,7 +165,7 @@ impl BaseStation {
             // Station has the same logic for Active and Sleep states because
             // when in Sleep state station still needs to process users, that are
             // already in the heap
-            BaseStationState::Active | BaseStationState::Sleep => user_min,
+            BaseStationState::Active | BaseStationState::Sleep(_) => user_min,
             BaseStationState::PowerUp(timestamp) => {
                 if timestamp < user_min.0 {
                     (timestamp, BaseStationEvent::PowerUp)
@@ -103,49 +191,52 @@ impl BaseStation {
         rng: &mut StdRng,
         use_logger: bool,
         logger: &mut Logger,
-    ) -> Option<User> {
+    ) -> Result<Option<User>, CassidyError> {
         match event {
             BaseStationEvent::AddUser => {
                 self.add_user_routine(cfg, rng, sim_state, use_logger, logger)
             }
             BaseStationEvent::ReleaseUser => {
                 // pop all finished users
-                if self.resources.len() == 0 {
-                    panic!("Internal error: Tried to release user from empty heap.");
-                }
-                let user = self.resources.pop().unwrap();
+                let user = match self.pop_user() {
+                    Some(user) => user,
+                    None => {
+                        return Err(CassidyError::Internal(
+                            "tried to release user from empty heap".to_owned(),
+                        ))
+                    }
+                };
                 if use_logger {
                     logger.log(
                         format!("UserRelease\tStation id: {}\t{}", self.id, &user),
                         sim_state.time,
-                        &cfg,
+                        cfg,
                     );
                 }
-                None
+                Ok(None)
             }
             BaseStationEvent::PowerUp => {
                 if use_logger {
                     logger.log(
                         format!("StateChange\tStation id: {}\tActive", self.id),
                         sim_state.time,
-                        &cfg,
+                        cfg,
                     );
                 }
                 self.state = BaseStationState::Active;
-                self.total_power += cfg.wakeup_power;
-                None
+                Ok(None)
             }
             BaseStationEvent::ShutDown => {
                 if use_logger {
                     logger.log(
                         format!("StateChange\tStation id: {}\tSleep", self.id),
                         sim_state.time,
-                        &cfg,
+                        cfg,
                     );
                 }
-                self.state = BaseStationState::Sleep;
-                self.total_power += cfg.wakeup_power;
-                None
+                self.state = BaseStationState::Sleep(0);
+                self.sleep_since = sim_state.time;
+                Ok(None)
             }
         }
     }
@@ -157,14 +248,52 @@ impl BaseStation {
         sim_state: &mut SimState,
         use_logger: bool,
         logger: &mut Logger,
+    ) -> Result<Option<User>, CassidyError> {
+        let user = match self.trace.as_mut() {
+            Some(trace) => {
+                let record = match trace.pop_front() {
+                    Some(record) => record,
+                    None => {
+                        return Err(CassidyError::Internal(
+                            "tried to add user from empty trace".to_owned(),
+                        ))
+                    }
+                };
+                self.next_user_add = trace.front().map_or(u64::MAX, |r| r.time);
+                record.user(sim_state.next_user_id)
+            }
+            None => {
+                self.next_user_add = self.next_arrival(sim_state.time, sim_state.lambda, cfg, rng);
+                User::new(sim_state.next_user_id, sim_state.time, rng, cfg)
+            }
+        };
+        Ok(self.add_user(user, cfg, sim_state, use_logger, logger))
+    }
+
+    /// Serves new user, which was created by this station or attached to it from outside,
+    /// e.g. in spatial mode. User is returned back for redirect if station cannot serve it.
+    pub fn add_user(
+        &mut self,
+        user: User,
+        cfg: &Config,
+        sim_state: &mut SimState,
+        use_logger: bool,
+        logger: &mut Logger,
     ) -> Option<User> {
-        self.next_user_add = sim_state.time + BaseStation::get_new_timestamp(sim_state.lambda, rng);
-        let user = User::new(sim_state.next_user_id, sim_state.time, rng, cfg);
         sim_state.next_user_id += 1;
+        if let Some(class) = user.class {
+            sim_state.class_users[class] += 1;
+        }
+        if let Some(count) = sim_state.priority_users.get_mut(user.priority) {
+            *count += 1;
+        }
         match self.state {
             BaseStationState::Active => {
-                if self.resources.len() >= cfg.resources_count {
-                    // All resources are being used. Return user for redirect
+                if !self.can_admit(&user) {
+                    if self.can_serve(user.blocks) {
+                        sim_state.admission_blocked += 1;
+                    }
+                    // All resources are being used or reserved. Return user for redirect
                     if use_logger {
                         logger.log(
                             format!(
@@ -172,7 +301,7 @@ impl BaseStation {
                                 self.id, &user, &self.next_user_add,
                             ),
                             sim_state.time,
-                            &cfg,
+                            cfg,
                         );
                     }
                     return Some(user);
@@ -183,43 +312,125 @@ impl BaseStation {
                         self.id, &user, &self.next_user_add
                     ),
                     sim_state.time,
-                    &cfg,
+                    cfg,
                 );
-                self.resources.push(user);
+                if let Some(count) = sim_state.priority_admitted.get_mut(user.priority) {
+                    *count += 1;
+                }
+                self.push_user(user);
                 None
             }
-            BaseStationState::Sleep
+            BaseStationState::Sleep(_)
             | BaseStationState::PowerUp(_)
             | BaseStationState::PowerDown(_) => Some(user),
         }
     }
 
-    /// Returns heap's usage as percentage
-    pub fn get_usage(&self, cfg: &Config) -> f64 {
-        (self.resources.len() as f64) / (cfg.resources_count as f64) * 100.0
+    fn push_user(&mut self, user: User) {
+        self.used_blocks += user.blocks;
+        self.resources.push(user);
+    }
+
+    fn pop_user(&mut self) -> Option<User> {
+        let user = self.resources.pop()?;
+        self.used_blocks -= user.blocks;
+        Some(user)
+    }
+
+    /// Returns usage of resource blocks as percentage
+    pub fn get_usage(&self) -> f64 {
+        (self.used_blocks as f64) / (self.params.resources_count as f64) * 100.0
     }
 
+    /// Returns number of used resource blocks
     pub fn get_usage_raw(&self) -> usize {
+        self.used_blocks
+    }
+
+    /// Returns number of served users
+    pub fn get_users_count(&self) -> usize {
         self.resources.len()
     }
 
+    /// Returns served users in arbitrary order
+    pub fn users(&self) -> impl Iterator<Item = &User> {
+        self.resources.iter()
+    }
+
+    /// Returns true if there is at least one free resource block
+    pub fn has_capacity(&self) -> bool {
+        self.can_serve(1)
+    }
+
+    /// Returns true if there are enough free resource blocks for user requesting given number of blocks
+    pub fn can_serve(&self, blocks: usize) -> bool {
+        self.get_free_resources() >= blocks
+    }
+
+    /// Returns true if admission control allows new arrival of given user at this station
+    pub fn can_admit(&self, user: &User) -> bool {
+        self.get_free_resources() >= user.blocks + self.params.admission.reserved(user.priority)
+    }
+
+    /// Returns number of free resource blocks
+    pub fn get_free_resources(&self) -> usize {
+        self.params.resources_count - self.used_blocks
+    }
+
     /// Pushes given user into inner heap.
-    /// If there is not enough space, user is discarded.
-    pub fn redirect_here(&mut self, cfg: &Config, user: User) -> Result<(), ()> {
-        if self.resources.len() >= cfg.resources_count {
-            return Err(());
+    /// If there is not enough space, user is returned back.
+    pub fn redirect_here(&mut self, user: User) -> Result<(), User> {
+        if !self.can_serve(user.blocks) {
+            return Err(user);
         }
-        self.resources.push(user);
+        self.push_user(user);
         Ok(())
     }
 
-    /// Pushes n users from given vector into inner heap.
-    /// If there is not enough space, remaining users are left in original vector
-    pub fn redirect_here_vec(&mut self, cfg: &Config, users: &mut Vec<User>, n: usize) {
-        let space = (cfg.resources_count - self.resources.len()).min(n);
-        let range = space.min(users.len());
-        for _ in 0..range {
-            self.resources.push(users.pop().unwrap());
+    /// Serves given user in place of active user with the lowest priority, which is lower than
+    /// priority of given user and frees enough resource blocks, including reserved ones. Among users with the same priority
+    /// the most recently started one is preempted. Returns preempted user,
+    /// or given user back if station is not active or there is no such user
+    pub fn preempt(&mut self, user: User, sim_state: &mut SimState) -> Result<User, User> {
+        if !self.is_active() {
+            return Err(user);
+        }
+        let free = self.get_free_resources();
+        let needed = user.blocks + self.params.admission.reserved(user.priority);
+        let victim = self
+            .resources
+            .iter()
+            .filter(|v| v.priority < user.priority && free + v.blocks >= needed)
+            .min_by(|a, b| a.priority.cmp(&b.priority).then(b.start.cmp(&a.start)))
+            .map(|v| v.id);
+        let Some(victim) = victim else {
+            return Err(user);
+        };
+        let victim = self.release_where(|v| v.id == victim).pop().unwrap();
+        for (counts, level) in [
+            (&mut sim_state.priority_admitted, user.priority),
+            (&mut sim_state.priority_preempted, victim.priority),
+        ] {
+            if let Some(count) = counts.get_mut(level) {
+                *count += 1;
+            }
+        }
+        self.push_user(user);
+        Ok(victim)
+    }
+
+    /// Pushes users from the end of given vector into inner heap, until they occupy
+    /// given number of blocks or next user does not fit.
+    /// Remaining users are left in original vector
+    pub fn redirect_here_vec(&mut self, users: &mut Vec<User>, blocks: usize) {
+        let mut taken = 0;
+        while let Some(user) = users.last() {
+            if taken + user.blocks > blocks || !self.can_serve(user.blocks) {
+                break;
+            }
+            taken += user.blocks;
+            let user = users.pop().unwrap();
+            self.push_user(user);
         }
     }
 
@@ -230,7 +441,7 @@ impl BaseStation {
         } else {
             let mut v = Vec::new();
             for _ in 0..self.resources.len() / 2 {
-                v.push(self.resources.pop().unwrap());
+                v.push(self.pop_user().unwrap());
             }
             v
         }
@@ -239,31 +450,166 @@ impl BaseStation {
     /// Pops all of internal heap content and returns it as vector
     pub fn release_all(&mut self) -> Vec<User> {
         let mut v: Vec<User> = Vec::new();
-        for _ in 0..self.resources.len() {
-            v.push(self.resources.pop().unwrap());
+        while let Some(user) = self.pop_user() {
+            v.push(user);
         }
         v
     }
 
-    pub fn is_active(&self) -> bool {
+    /// Removes users, for which given function returns true, and returns them as vector.
+    /// Function can modify users, but it must not change their end time
+    pub fn release_where(&mut self, mut f: impl FnMut(&mut User) -> bool) -> Vec<User> {
+        let mut released = Vec::new();
+        for mut user in self.resources.drain().collect::<Vec<User>>() {
+            if f(&mut user) {
+                self.used_blocks -= user.blocks;
+                released.push(user);
+            } else {
+                self.resources.push(user);
+            }
+        }
+        released
+    }
+
+    /// Returns delay [us] of waking up from current sleep level
+    pub fn wakeup_delay(&self) -> u64 {
+        let level = match self.state {
+            BaseStationState::Sleep(level) => level,
+            _ => 0,
+        };
+        self.params.sleep_levels[level].wakeup_delay * 1000
+    }
+
+    /// Moves sleeping station into the next deeper sleep level, if there is any.
+    /// Returns true if station was demoted
+    pub fn demote(&mut self, time: u64) -> bool {
         match self.state {
-            BaseStationState::Active => true,
+            BaseStationState::Sleep(level) if level + 1 < self.params.sleep_levels.len() => {
+                self.state = BaseStationState::Sleep(level + 1);
+                self.sleep_since = time;
+                true
+            }
             _ => false,
         }
     }
 
-    pub fn accumulate_counters(&mut self, dt: u64, cfg: &Config) {
+    /// Power [W] of active station without load
+    fn idle_power(&self) -> f64 {
+        self.params.power_model.power(self.params.active_power, 0.0)
+    }
+
+    /// Starts waking up from current sleep level, which takes its wakeup_delay
+    pub fn power_up(&mut self, time: u64) {
+        let level = match self.state {
+            BaseStationState::Sleep(level) => level,
+            _ => 0,
+        };
+        self.transition = Transition {
+            start: time,
+            duration: self.wakeup_delay(),
+            powers: (self.params.sleep_levels[level].power, self.idle_power()),
+        };
+        self.state = BaseStationState::PowerUp(time + self.transition.duration);
+    }
+
+    /// Starts going to the first sleep level, which takes its wakeup_delay
+    pub fn power_down(&mut self, time: u64) {
+        let level = &self.params.sleep_levels[0];
+        self.transition = Transition {
+            start: time,
+            duration: level.wakeup_delay * 1000,
+            powers: (self.idle_power(), level.power),
+        };
+        self.state = BaseStationState::PowerDown(time + self.transition.duration);
+    }
+
+    pub fn is_active(&self) -> bool {
+        matches!(self.state, BaseStationState::Active)
+    }
+
+    /// Accumulates counters from last update up to given timestamp.
+    /// Must be called before each change of station state or usage.
+    pub fn update_counters(&mut self, time: u64) {
+        let dt = time - self.last_update;
+        let last_update = self.last_update;
+        self.last_update = time;
         let dp = match self.state {
-            BaseStationState::Active => dt as f64 * cfg.active_power,
-            BaseStationState::Sleep => {
+            BaseStationState::Active => {
+                let power = self
+                    .params
+                    .power_model
+                    .power(self.params.active_power, self.get_usage());
+                dt as f64 * power
+            }
+            BaseStationState::Sleep(level) => {
                 self.sleep_time += dt;
-                dt as f64 * cfg.sleep_power
+                self.sleep_level_times[level] += dt;
+                dt as f64 * self.params.sleep_levels[level].power
+            }
+            BaseStationState::PowerUp(_) | BaseStationState::PowerDown(_) => {
+                let t = self.transition;
+                if t.duration == 0 {
+                    0.0
+                } else {
+                    let progress = |time: u64| {
+                        (time.saturating_sub(t.start) as f64 / t.duration as f64).min(1.0)
+                    };
+                    let power = self.params.transition_power.mean_power(
+                        progress(last_update),
+                        progress(time),
+                        t.powers,
+                        self.params.wakeup_power,
+                    );
+                    dt as f64 * power
+                }
             }
-            BaseStationState::PowerUp(_) => 0.0,
-            BaseStationState::PowerDown(_) => 0.0,
         };
         self.total_power += dp;
-        self.total_usage += dt as f64 * self.get_usage(&cfg);
+        self.total_usage += dt as f64 * self.get_usage();
+        if !self.hourly_energy.is_empty() && dt > 0 {
+            let baseline = dt as f64
+                * self
+                    .params
+                    .power_model
+                    .power(self.params.active_power, self.get_usage());
+            self.add_hourly(last_update, time, dp, baseline);
+        }
+    }
+
+    /// Splits energy consumed between `from` and `to` into hours of the day
+    fn add_hourly(&mut self, from: u64, to: u64, energy: f64, baseline: f64) {
+        let mut t = from;
+        while t < to {
+            let end = ((t / HOUR + 1) * HOUR).min(to);
+            let share = (end - t) as f64 / (to - from) as f64;
+            let hour = ((t / HOUR) % 24) as usize;
+            self.hourly_energy[hour] += energy * share;
+            self.hourly_baseline[hour] += baseline * share;
+            t = end;
+        }
+    }
+
+    /// Energy consumed [kWh] weighted by hourly `schedule`, e.g. price or carbon intensity,
+    /// together with the same value for always-on station
+    pub fn priced_energy(&self, schedule: &[f64]) -> (f64, f64) {
+        let price = |energy: &Vec<f64>| {
+            energy
+                .iter()
+                .enumerate()
+                .map(|(hour, e)| e / 3_600_000_000_000.0 * schedule[hour % schedule.len()])
+                .sum()
+        };
+        (price(&self.hourly_energy), price(&self.hourly_baseline))
+    }
+
+    /// Discards everything accumulated so far, e.g. at the end of warm-up period
+    pub fn reset_counters(&mut self) {
+        self.total_power = 0.0;
+        self.total_usage = 0.0;
+        self.sleep_time = 0;
+        self.sleep_level_times.fill(0);
+        self.hourly_energy.fill(0.0);
+        self.hourly_baseline.fill(0.0);
     }
 
     pub fn get_results(&self, total_time: u64) -> BaseStationResult {
@@ -271,6 +617,13 @@ impl BaseStation {
             average_power: self.total_power / total_time as f64,
             average_usage: self.total_usage / total_time as f64,
             average_sleep_time: self.sleep_time as f64 / total_time as f64,
+            sleep_level_times: self
+                .sleep_level_times
+                .iter()
+                .map(|t| *t as f64 / total_time as f64)
+                .collect(),
+            // [W * us] -> [Wh]
+            energy: self.total_power / 3_600_000_000.0,
         }
     }
 }
@@ -279,14 +632,24 @@ impl BaseStation {
 impl BaseStation {
     #[allow(dead_code)]
     pub fn force_add_user(&mut self, user: User) {
-        self.resources.push(user);
+        self.push_user(user);
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::{BaseStation, BaseStationEvent, BaseStationState};
-    use crate::{config::Config, logger::Logger, sim_container::SimState, user::User};
+    use crate::{
+        arrival::create_process,
+        config::{
+            AdmissionControl, ArrivalProcessKind, Config, LambdaPoint, LambdaProfile, PowerModel,
+            PowerPoint, PriorityConfig, SleepLevel, StationConfig, TransitionPower,
+        },
+        error::CassidyError,
+        logger::Logger,
+        sim_container::SimState,
+        user::User,
+    };
     use rand::{rngs::StdRng, SeedableRng};
     use std::{io::Write, path::PathBuf, process::Command};
 
@@ -294,8 +657,10 @@ mod test {
     fn add_release_user() {
         // Test adding users to max capacity and check return type
         let event = BaseStationEvent::AddUser;
-        let mut cfg = Config::default();
-        cfg.resources_count = 10;
+        let cfg = Config {
+            resources_count: 10,
+            ..Default::default()
+        };
         let mut logger =
             Logger::new(false, &cfg, &PathBuf::from("test_add_release_user.log")).unwrap();
         let mut rng = StdRng::seed_from_u64(1);
@@ -304,88 +669,107 @@ mod test {
         sim_state.lambda = 1.0;
         sim_state.time = 0;
         for _ in 0..10 {
-            let res =
-                station.execute_event(&event, &cfg, &mut sim_state, &mut rng, false, &mut logger);
-            assert!(res.is_none() == true);
+            let res = station
+                .execute_event(&event, &cfg, &mut sim_state, &mut rng, false, &mut logger)
+                .unwrap();
+            assert!(res.is_none());
         }
-        let res = station.execute_event(&event, &cfg, &mut sim_state, &mut rng, false, &mut logger);
-        assert!(res.is_some() == true);
+        let res = station
+            .execute_event(&event, &cfg, &mut sim_state, &mut rng, false, &mut logger)
+            .unwrap();
+        assert!(res.is_some());
         // Test releasing all users and  return type - should not panic
         let event = BaseStationEvent::ReleaseUser;
         for _ in 0..10 {
-            let res =
-                station.execute_event(&event, &cfg, &mut sim_state, &mut rng, false, &mut logger);
-            assert!(res.is_none() == true);
+            let res = station
+                .execute_event(&event, &cfg, &mut sim_state, &mut rng, false, &mut logger)
+                .unwrap();
+            assert!(res.is_none());
         }
         logger.flush();
     }
 
     #[test]
-    #[should_panic]
-    fn release_user_panic() {
+    fn release_user_error() {
         // Test release from empty heap
-        let mut cfg = Config::default();
-        cfg.resources_count = 10;
+        let cfg = Config {
+            resources_count: 10,
+            ..Default::default()
+        };
         let mut logger =
-            Logger::new(false, &cfg, &PathBuf::from("test_release_user_panic.log")).unwrap();
+            Logger::new(false, &cfg, &PathBuf::from("test_release_user_error.log")).unwrap();
         let mut rng = StdRng::seed_from_u64(1);
         let mut station = BaseStation::new(1, &cfg, 1.0, &mut rng);
         let mut sim_state = SimState::new(&cfg);
         let event = BaseStationEvent::ReleaseUser;
-        let _ = station.execute_event(&event, &cfg, &mut sim_state, &mut rng, false, &mut logger);
+        let err = station
+            .execute_event(&event, &cfg, &mut sim_state, &mut rng, false, &mut logger)
+            .unwrap_err();
+        assert!(matches!(err, CassidyError::Internal(_)));
+        assert_eq!(err.exit_code(), 70);
     }
 
     #[test]
     fn add_user_all_states() {
         // Adding (redirect) from states different from Active
-        let mut cfg = Config::default();
-        cfg.resources_count = 10;
+        let cfg = Config {
+            resources_count: 10,
+            ..Default::default()
+        };
         let mut logger =
             Logger::new(false, &cfg, &PathBuf::from("test_add_user_all_states.log")).unwrap();
         let mut rng = StdRng::seed_from_u64(1);
         let mut sim_state = SimState::new(&cfg);
         let mut station = BaseStation::new(1, &cfg, 1.0, &mut rng);
         // test add (redirect) during sleep state
-        station.state = BaseStationState::Sleep;
-        let res = station.execute_event(
-            &BaseStationEvent::AddUser,
-            &cfg,
-            &mut sim_state,
-            &mut rng,
-            false,
-            &mut logger,
-        );
-        assert!(res.is_some() == true);
-        assert!(station.resources.len() == 0);
+        station.state = BaseStationState::Sleep(0);
+        let res = station
+            .execute_event(
+                &BaseStationEvent::AddUser,
+                &cfg,
+                &mut sim_state,
+                &mut rng,
+                false,
+                &mut logger,
+            )
+            .unwrap();
+        assert!(res.is_some());
+        assert!(station.resources.is_empty());
         // test add (redirect) during power-uo/down state
         station.state = BaseStationState::PowerUp(10);
-        let res = station.execute_event(
-            &BaseStationEvent::AddUser,
-            &cfg,
-            &mut sim_state,
-            &mut rng,
-            false,
-            &mut logger,
-        );
-        assert!(res.is_some() == true);
-        assert!(station.resources.len() == 0);
+        let res = station
+            .execute_event(
+                &BaseStationEvent::AddUser,
+                &cfg,
+                &mut sim_state,
+                &mut rng,
+                false,
+                &mut logger,
+            )
+            .unwrap();
+        assert!(res.is_some());
+        assert!(station.resources.is_empty());
         station.state = BaseStationState::PowerDown(10);
-        let res = station.execute_event(
-            &BaseStationEvent::AddUser,
-            &cfg,
-            &mut sim_state,
-            &mut rng,
-            false,
-            &mut logger,
-        );
-        assert!(res.is_some() == true);
-        assert!(station.resources.len() == 0);
+        let res = station
+            .execute_event(
+                &BaseStationEvent::AddUser,
+                &cfg,
+                &mut sim_state,
+                &mut rng,
+                false,
+                &mut logger,
+            )
+            .unwrap();
+        assert!(res.is_some());
+        assert!(station.resources.is_empty());
     }
 
     #[test]
     fn get_event() {
-        let mut cfg = Config::default();
-        cfg.resources_count = 10;
+        let cfg = Config {
+            resources_count: 10,
+            ..Default::default()
+        };
         let mut logger =
             Logger::new(false, &cfg, &PathBuf::from("test_add_user_all_states.log")).unwrap();
         let mut rng = StdRng::seed_from_u64(1);
@@ -394,29 +778,23 @@ mod test {
 
         // test active state
         station.state = BaseStationState::Active;
-        station.force_add_user(User {
-            id: 1,
-            start: 0,
-            end: 10,
-        });
-        station.force_add_user(User {
-            id: 2,
-            start: 0,
-            end: 20,
-        });
+        station.force_add_user(User::test(1, 10));
+        station.force_add_user(User::test(2, 20));
         assert!(station.resources.len() == 2);
         station.next_user_add = 15;
         let res = station.get_next_event();
         assert_eq!(res.0, 10);
         assert!(std::matches!(res.1, BaseStationEvent::ReleaseUser));
-        station.execute_event(
-            &BaseStationEvent::ReleaseUser,
-            &cfg,
-            &mut sim_state,
-            &mut rng,
-            false,
-            &mut logger,
-        );
+        station
+            .execute_event(
+                &BaseStationEvent::ReleaseUser,
+                &cfg,
+                &mut sim_state,
+                &mut rng,
+                false,
+                &mut logger,
+            )
+            .unwrap();
         assert_eq!(station.resources.len(), 1);
         let res = station.get_next_event();
         assert_eq!(res.0, 15);
@@ -424,23 +802,21 @@ mod test {
 
         // test sleep state
         station.resources.clear();
-        station.force_add_user(User {
-            id: 1,
-            start: 0,
-            end: 10,
-        });
-        station.state = BaseStationState::Sleep;
+        station.force_add_user(User::test(1, 10));
+        station.state = BaseStationState::Sleep(0);
         let res = station.get_next_event();
         assert_eq!(res.0, 10);
         assert!(std::matches!(res.1, BaseStationEvent::ReleaseUser));
-        station.execute_event(
-            &BaseStationEvent::ReleaseUser,
-            &cfg,
-            &mut sim_state,
-            &mut rng,
-            false,
-            &mut logger,
-        );
+        station
+            .execute_event(
+                &BaseStationEvent::ReleaseUser,
+                &cfg,
+                &mut sim_state,
+                &mut rng,
+                false,
+                &mut logger,
+            )
+            .unwrap();
         let res = station.get_next_event();
         assert_eq!(res.0, 15);
         assert!(std::matches!(res.1, BaseStationEvent::AddUser));
@@ -469,8 +845,10 @@ mod test {
 
     #[test]
     fn test_add_release_order() {
-        let mut cfg = Config::default();
-        cfg.resources_count = 10;
+        let cfg = Config {
+            resources_count: 10,
+            ..Default::default()
+        };
         let mut logger =
             Logger::new(true, &cfg, &PathBuf::from("tests/add_release_order.log")).unwrap();
         let mut rng = StdRng::seed_from_u64(1);
@@ -478,27 +856,31 @@ mod test {
         let mut station = BaseStation::new(1, &cfg, 1.0, &mut rng);
         // add users to max capacity
         for _ in 0..10 {
-            let res = station.execute_event(
-                &BaseStationEvent::AddUser,
-                &cfg,
-                &mut sim_state,
-                &mut rng,
-                true,
-                &mut logger,
-            );
-            assert_eq!(res.is_none(), true);
+            let res = station
+                .execute_event(
+                    &BaseStationEvent::AddUser,
+                    &cfg,
+                    &mut sim_state,
+                    &mut rng,
+                    true,
+                    &mut logger,
+                )
+                .unwrap();
+            assert!(res.is_none());
         }
         // release all users
         for _ in 0..10 {
-            let res = station.execute_event(
-                &BaseStationEvent::ReleaseUser,
-                &cfg,
-                &mut sim_state,
-                &mut rng,
-                true,
-                &mut logger,
-            );
-            assert_eq!(res.is_none(), true);
+            let res = station
+                .execute_event(
+                    &BaseStationEvent::ReleaseUser,
+                    &cfg,
+                    &mut sim_state,
+                    &mut rng,
+                    true,
+                    &mut logger,
+                )
+                .unwrap();
+            assert!(res.is_none());
         }
         logger.flush();
         // compare log to reference
@@ -524,15 +906,405 @@ mod test {
         assert_eq!(diff.status.code().unwrap(), 0);
     }
 
+    #[test]
+    fn multi_block_users() {
+        let cfg = Config {
+            resources_count: 10,
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut station = BaseStation::new(0, &cfg, 1.0, &mut rng);
+        let user = |id, blocks| {
+            User::test(id, 10 + id as u64)
+                .with_blocks(blocks)
+                .with_class(0)
+        };
+        assert!(station.redirect_here(user(0, 4)).is_ok());
+        assert!(station.redirect_here(user(1, 4)).is_ok());
+        assert_eq!(station.get_usage(), 80.0);
+        assert_eq!(station.get_usage_raw(), 8);
+        assert_eq!(station.get_users_count(), 2);
+        assert!(station.can_serve(2));
+        assert!(!station.can_serve(3));
+        assert!(station.redirect_here(user(2, 3)).is_err());
+
+        // users are taken from the end of vector until budget of blocks is reached
+        let mut users = vec![user(3, 1), user(4, 1), user(5, 1)];
+        station.redirect_here_vec(&mut users, 1);
+        assert_eq!(users.len(), 2);
+        station.redirect_here_vec(&mut users, usize::MAX);
+        assert_eq!(users.len(), 1);
+        assert_eq!(station.get_free_resources(), 0);
+
+        let released = station.release_all();
+        assert_eq!(released.len(), 4);
+        assert_eq!(station.get_usage_raw(), 0);
+    }
+
+    #[test]
+    fn preemption() {
+        let cfg = Config {
+            resources_count: 4,
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut station = BaseStation::new(0, &cfg, 1.0, &mut rng);
+        let mut sim_state = SimState::new(&Config {
+            priorities: Some(PriorityConfig {
+                shares: vec![1.0; 3],
+                preemption: true,
+            }),
+            ..cfg.clone()
+        });
+        let user = |id, start, blocks, priority| {
+            User::test(id, 100)
+                .with_start(start)
+                .with_blocks(blocks)
+                .with_priority(priority)
+        };
+        station.force_add_user(user(0, 0, 1, 0));
+        station.force_add_user(user(1, 5, 1, 0));
+        station.force_add_user(user(2, 0, 2, 1));
+        // users with the same priority cannot be preempted
+        assert!(station.preempt(user(3, 10, 1, 0), &mut sim_state).is_err());
+        // the most recently started user with the lowest priority
+        let victim = station.preempt(user(4, 10, 1, 2), &mut sim_state).unwrap();
+        assert_eq!(victim.id, 1);
+        // only user 2 frees enough blocks
+        let victim = station.preempt(user(5, 10, 2, 2), &mut sim_state).unwrap();
+        assert_eq!(victim.id, 2);
+        assert_eq!(station.get_usage_raw(), 4);
+        assert_eq!(sim_state.priority_admitted, vec![0, 0, 2]);
+        assert_eq!(sim_state.priority_preempted, vec![1, 1, 0]);
+
+        station.state = BaseStationState::Sleep(0);
+        assert!(station.preempt(user(6, 10, 1, 2), &mut sim_state).is_err());
+    }
+
+    #[test]
+    fn sleep_levels() {
+        let level = |name: &str, power| SleepLevel {
+            name: name.to_owned(),
+            power,
+            wakeup_delay: 10,
+            idle_time: 100,
+        };
+        let cfg = Config {
+            sleep_levels: vec![level("light", 20.0), level("deep", 5.0)],
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut station = BaseStation::new(0, &cfg, 1.0, &mut rng);
+        station.state = BaseStationState::Sleep(0);
+        station.update_counters(10);
+        assert!(station.demote(10));
+        station.update_counters(40);
+        let res = station.get_results(40);
+        assert_eq!(res.average_power, (10.0 * 20.0 + 30.0 * 5.0) / 40.0);
+        assert_eq!(res.sleep_level_times, vec![0.25, 0.75]);
+        assert_eq!(res.average_sleep_time, 1.0);
+
+        // without configured levels station sleeps with sleep_power
+        let station = BaseStation::new(0, &Config::default(), 1.0, &mut rng);
+        assert_eq!(station.params.sleep_levels.len(), 1);
+        assert_eq!(
+            station.params.sleep_levels[0].power,
+            Config::default().sleep_power
+        );
+    }
+
+    #[test]
+    fn hourly_energy() {
+        let mut tariff = vec![0.0; 24];
+        tariff[0] = 1.0;
+        tariff[1] = 2.0;
+        let cfg = Config {
+            sleep_power: 1000.0,
+            tariff: Some(tariff.clone()),
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut station = BaseStation::new(0, &cfg, 1.0, &mut rng);
+        station.state = BaseStationState::Sleep(0);
+        // 1.5 h of sleep is split into 1 kWh in first hour and 0.5 kWh in second hour
+        station.update_counters(5_400_000_000);
+        let (cost, baseline) = station.priced_energy(&tariff);
+        assert!((cost - 2.0).abs() < 1e-9);
+        let active = station.params.power_model.power(cfg.active_power, 0.0);
+        assert!((baseline - 2.0 * active / 1000.0).abs() < 1e-9);
+        // single value applies to every hour
+        let (cost, _) = station.priced_energy(&[0.5]);
+        assert!((cost - 0.75).abs() < 1e-9);
+        station.reset_counters();
+        assert_eq!(station.priced_energy(&tariff), (0.0, 0.0));
+        // nothing is tracked without schedules
+        let mut station = BaseStation::new(0, &Config::default(), 1.0, &mut rng);
+        station.update_counters(5_400_000_000);
+        assert_eq!(station.priced_energy(&tariff), (0.0, 0.0));
+    }
+
+    #[test]
+    fn transition_power() {
+        let cfg = Config {
+            sleep_power: 1.0,
+            active_power: 201.0,
+            wakeup_delay: 1,
+            transition_power: TransitionPower::Ramp,
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut station = BaseStation::new(0, &cfg, 1.0, &mut rng);
+        station.state = BaseStationState::Sleep(0);
+        station.power_up(0);
+        assert!(matches!(station.state, BaseStationState::PowerUp(1000)));
+        // ramp from 1 W to 201 W during 1000 us
+        station.update_counters(500);
+        assert_eq!(station.total_power, 500.0 * (1.0 + 101.0) / 2.0);
+        station.update_counters(1000);
+        assert_eq!(station.total_power, 1000.0 * (1.0 + 201.0) / 2.0);
+
+        // constant wakeup_power during transition
+        let cfg = Config {
+            wakeup_delay: 2,
+            ..Default::default()
+        };
+        let mut station = BaseStation::new(0, &cfg, 1.0, &mut rng);
+        station.power_down(0);
+        station.update_counters(2000);
+        assert_eq!(station.total_power, 2000.0 * cfg.wakeup_power);
+        let mut logger = Logger::new(false, &cfg, &PathBuf::from("tests/transition.log")).unwrap();
+        station
+            .execute_event(
+                &BaseStationEvent::ShutDown,
+                &cfg,
+                &mut SimState::new(&cfg),
+                &mut rng,
+                false,
+                &mut logger,
+            )
+            .unwrap();
+        // no extra energy is added at the end of transition
+        assert_eq!(station.total_power, 2000.0 * cfg.wakeup_power);
+        assert_eq!(
+            station.get_results(3_600_000).energy,
+            0.002 * cfg.wakeup_power / 3600.0
+        );
+    }
+
+    #[test]
+    fn admission_control() {
+        let cfg = Config {
+            resources_count: 4,
+            admission: AdmissionControl::TrunkReservation {
+                thresholds: vec![2, 1],
+            },
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut station = BaseStation::new(0, &cfg, 1.0, &mut rng);
+        let user = |id, priority| User::test(id, 100).with_priority(priority);
+        station.force_add_user(user(0, 0));
+        assert!(station.can_admit(&user(1, 0)));
+        station.force_add_user(user(1, 0));
+        // 2 free blocks are reserved for higher priorities
+        assert!(!station.can_admit(&user(2, 0)));
+        assert!(station.can_admit(&user(2, 1)));
+        station.force_add_user(user(2, 1));
+        assert!(!station.can_admit(&user(3, 1)));
+        assert!(station.can_admit(&user(3, 2)));
+        // redirected users are not restricted
+        assert!(station.redirect_here(user(3, 0)).is_ok());
+    }
+
+    #[test]
+    fn station_overrides() {
+        let cfg = Config {
+            resources_count: 10,
+            stations: vec![
+                StationConfig::default(),
+                StationConfig {
+                    resources_count: Some(4),
+                    active_power: Some(50.0),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut small = BaseStation::new(1, &cfg, 1.0, &mut rng);
+        let mut large = BaseStation::new(2, &cfg, 1.0, &mut rng);
+        assert_eq!(small.params.resources_count, 4);
+        assert_eq!(large.params.resources_count, 10);
+        assert_eq!(large.params.active_power, cfg.active_power);
+        for id in 0..4 {
+            small.force_add_user(User::test(id, 10));
+            large.force_add_user(User::test(id, 10));
+        }
+        assert!(!small.has_capacity());
+        assert_eq!(small.get_usage(), 100.0);
+        assert_eq!(large.get_usage(), 40.0);
+        small.update_counters(10);
+        large.update_counters(10);
+        assert_eq!(small.get_results(10).average_power, 50.0);
+        assert_eq!(large.get_results(10).average_power, cfg.active_power);
+    }
+
+    #[test]
+    fn power_model() {
+        let cfg = Config {
+            resources_count: 10,
+            power_model: PowerModel::Linear {
+                p0: 100.0,
+                delta_p: 4.0,
+                p_max: 20.0,
+            },
+            stations: vec![StationConfig {
+                power_model: Some(PowerModel::Piecewise {
+                    points: vec![
+                        PowerPoint {
+                            usage: 20.0,
+                            power: 100.0,
+                        },
+                        PowerPoint {
+                            usage: 60.0,
+                            power: 300.0,
+                        },
+                    ],
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut piecewise = BaseStation::new(0, &cfg, 1.0, &mut rng);
+        let mut linear = BaseStation::new(1, &cfg, 1.0, &mut rng);
+        // empty stations
+        piecewise.update_counters(10);
+        linear.update_counters(10);
+        // 40% usage
+        for id in 0..4 {
+            piecewise.force_add_user(User::test(id, 100));
+            linear.force_add_user(User::test(id, 100));
+        }
+        piecewise.update_counters(20);
+        linear.update_counters(20);
+        assert_eq!(piecewise.total_power, 10.0 * 100.0 + 10.0 * 200.0);
+        assert_eq!(linear.total_power, 10.0 * 100.0 + 10.0 * 132.0);
+        // usage above last point
+        for id in 4..10 {
+            piecewise.force_add_user(User::test(id, 100));
+        }
+        piecewise.update_counters(30);
+        assert_eq!(
+            piecewise.total_power,
+            10.0 * 100.0 + 10.0 * 200.0 + 10.0 * 300.0
+        );
+    }
+
+    #[test]
+    fn lambda_profile() {
+        // linear ramp from 0 to 2 during the first hour and back to 0 during the second one
+        let hour = 3600.0 * 1_000_000.0;
+        let cfg = Config {
+            lambda: 10.0,
+            lambda_coefs: vec![
+                LambdaPoint {
+                    time: hour,
+                    coef: 0.0,
+                },
+                LambdaPoint {
+                    time: hour,
+                    coef: 2.0,
+                },
+            ],
+            lambda_profile: LambdaProfile::Linear,
+            stations: vec![
+                StationConfig::default(),
+                StationConfig {
+                    phase_offset: Some(1.0),
+                    ..Default::default()
+                },
+                StationConfig {
+                    lambda_profile: Some(LambdaProfile::Step),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        // number of arrivals in both halves of the first hour
+        let mut count_arrivals = |id: usize| {
+            let mut station = BaseStation::new(id, &cfg, 0.0, &mut rng);
+            let mut counts = [0; 2];
+            let mut time = station.next_user_add;
+            while time < hour as u64 {
+                counts[(time as f64 / hour * 2.0) as usize] += 1;
+                time = station.next_arrival(time, 0.0, &cfg, &mut rng);
+            }
+            counts
+        };
+        // integral of lambda: 10 * 3600 * 0.25 and 10 * 3600 * 0.75
+        let counts = count_arrivals(0);
+        assert!((8700..9300).contains(&counts[0]), "{:?}", counts);
+        assert!((26200..27800).contains(&counts[1]), "{:?}", counts);
+        // station shifted by 1 hour is already in decreasing part of profile
+        let counts = count_arrivals(1);
+        assert!((26200..27800).contains(&counts[0]), "{:?}", counts);
+        assert!((8700..9300).contains(&counts[1]), "{:?}", counts);
+        // own step profile has no arrivals in the first hour
+        assert_eq!(count_arrivals(2), [0, 0]);
+    }
+
+    #[test]
+    fn station_lambda() {
+        // step profile: coefficient 1 during the first hour and 3 during the second one
+        let hour = 3600.0 * 1_000_000.0;
+        let cfg = Config {
+            lambda: 2.0,
+            lambda_coefs: vec![
+                LambdaPoint {
+                    time: hour,
+                    coef: 1.0,
+                },
+                LambdaPoint {
+                    time: hour,
+                    coef: 3.0,
+                },
+            ],
+            stations: vec![
+                StationConfig {
+                    lambda_weight: Some(2.0),
+                    ..Default::default()
+                },
+                StationConfig {
+                    lambda_weight: Some(0.5),
+                    phase_offset: Some(1.0),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        let heavy = BaseStation::new(0, &cfg, 2.0, &mut rng);
+        let shifted = BaseStation::new(1, &cfg, 2.0, &mut rng);
+        let half_hour = (hour / 2.0) as u64;
+        // lambda * weight * coef, shifted station is one phase behind
+        assert_eq!(heavy.params.lambda_at(half_hour, &cfg), 4.0);
+        assert_eq!(shifted.params.lambda_at(half_hour, &cfg), 3.0);
+        assert_eq!(heavy.params.lambda_at(3 * half_hour, &cfg), 12.0);
+        assert_eq!(shifted.params.lambda_at(3 * half_hour, &cfg), 1.0);
+    }
+
     #[test]
     fn generate_lambda() {
         let mut rng = StdRng::seed_from_u64(1);
+        let mut process = create_process(&ArrivalProcessKind::Poisson);
         let mut file = std::fs::File::create("tests/station_rng.log")
             .expect("Couldn't create log file for station rng test.");
         for lambda in [1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0] {
             let _ = file.write(format!("{},", lambda).as_bytes());
             for _ in 0..1000 {
-                let x = BaseStation::get_new_timestamp(lambda, &mut rng);
+                let x = process.next_arrival(0, lambda, &mut rng);
                 let _ = file.write(format!("{},", x).as_bytes());
             }
             let _ = file.write("\n".as_bytes());
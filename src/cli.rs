@@ -0,0 +1,105 @@
+use std::path::PathBuf;
+
+use cassidy::{CassidyError, Config, SimulationBuilder, WalkOverConfig};
+use clap::Parser;
+
+#[derive(Debug, Parser)]
+#[command(version, about, long_about = None)]
+pub struct Cli {
+    /// Path to config file. This option cannot be used with any other config switch.
+    #[arg(long, value_name = "path")]
+    pub with_config: Option<PathBuf>,
+    /// Seed for random number generator
+    #[arg(long, value_name = "u64")]
+    pub seed: Option<u64>,
+    /// Generate event log file
+    #[arg(long)]
+    pub log: bool,
+    /// Time (in hours) simulation will be run for. Maximum precision is 1ms
+    #[arg(long, value_name = "time")]
+    pub duration: f64,
+    /// Simulation iterations count
+    #[arg(long, value_name = "u32", default_value_t = 1)]
+    pub iterations: u32,
+    /// Enable sleep state logic
+    #[arg(long)]
+    pub enable_sleep: bool,
+    /// Save default config
+    #[arg(long, value_name = "path")]
+    pub save_default_config: Option<PathBuf>,
+    /// Show partial results from all iterations
+    #[arg(long)]
+    pub show_partial_results: bool,
+    /// Log simulation process in binary format
+    #[arg(long)]
+    pub log_wave: bool,
+    /// Binary log sampling divider
+    #[arg(long, value_name = "u32", default_value_t = 1)]
+    pub samples: usize,
+    /// Enable iteration over given parameter based on given config
+    #[arg(long, value_name = "path")]
+    pub walk_over: Option<PathBuf>,
+    /// Time (in hours) from the beginning of simulation excluded from statistics. Overrides config value
+    #[arg(long, value_name = "hours")]
+    pub warmup: Option<f64>,
+    /// Detect warm-up period automatically with MSER-5 rule
+    #[arg(long, conflicts_with = "warmup")]
+    pub auto_warmup: bool,
+    /// Level of confidence intervals calculated across iterations
+    #[arg(long, value_name = "f64", default_value_t = 0.95)]
+    pub confidence_level: f64,
+}
+
+impl Cli {
+    pub fn create_config(&self) -> Result<Config, CassidyError> {
+        match &self.with_config {
+            Some(file_path) => Config::from_file(file_path),
+            None => Ok(Config::default()),
+        }
+    }
+
+    pub fn create_walk_over_config(&self) -> Result<Option<WalkOverConfig>, CassidyError> {
+        match &self.walk_over {
+            Some(file_path) => WalkOverConfig::from_file(file_path).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Creates simulation builder with run options taken from command line
+    pub fn create_builder(&self, cfg: Config) -> SimulationBuilder {
+        let mut builder = SimulationBuilder::new(cfg)
+            .duration(self.duration)
+            .iterations(self.iterations)
+            .enable_sleep(self.enable_sleep)
+            .log(self.log)
+            .log_wave(self.log_wave)
+            .samples(self.samples)
+            .show_partial_results(self.show_partial_results)
+            .confidence_level(self.confidence_level);
+        if let Some(seed) = self.seed {
+            builder = builder.seed(seed);
+        }
+        // explicitly given warm-up takes precedence over automatic detection from config
+        if let Some(warmup) = self.warmup {
+            builder = builder.warmup(warmup).auto_warmup(false);
+        }
+        if self.auto_warmup {
+            builder = builder.auto_warmup(true);
+        }
+        builder
+    }
+
+    pub fn validate(self) -> Result<Cli, CassidyError> {
+        if let Some(path) = &self.with_config {
+            if let Some(file_dir) = path.parent() {
+                if !file_dir.exists() {
+                    return Err(CassidyError::Usage(format!(
+                        "Given path to config file: '{}' does not exists",
+                        &path.display()
+                    )));
+                }
+            }
+        }
+        Ok(self)
+    }
+}
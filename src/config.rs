@@ -1,130 +1,1235 @@
 use std::{
-    fmt::Display,
     fs::File,
     io::{Read, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
-use clap::Parser;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Parser)]
-#[command(version, about, long_about = None)]
-pub struct Cli {
-    /// Path to config file. This option cannot be used with any other config switch.
-    #[arg(long, value_name = "path")]
-    pub with_config: Option<PathBuf>,
-    /// Seed for random number generator
-    #[arg(long, value_name = "u64")]
-    pub seed: Option<u64>,
-    /// Generate event log file
-    #[arg(long)]
-    pub log: bool,
-    /// Time (in hours) simulation will be run for. Maximum precision is 1ms
-    #[arg(long, value_name = "time")]
-    pub duration: f64,
-    /// Simulation iterations count
-    #[arg(long, value_name = "u32", default_value_t = 1)]
-    pub iterations: u32,
-    /// Enable sleep state logic
-    #[arg(long)]
-    pub enable_sleep: bool,
-    /// Save default config
-    #[arg(long, value_name = "path")]
-    pub save_default_config: Option<PathBuf>,
-    /// Show partial results from all iterations
-    #[arg(long)]
-    pub show_partial_results: bool,
-    /// Log simulation process in binary format
-    #[arg(long)]
-    pub log_wave: bool,
-    /// Binary log sampling divider
-    #[arg(long, value_name = "u32", default_value_t = 1)]
-    pub samples: usize,
-    /// Enable iteration over given parameter based on given config
-    #[arg(long, value_name = "path")]
-    pub walk_over: Option<PathBuf>,
-}
-
-impl Cli {
-    pub fn create_config(&self) -> Result<Config, String> {
-        if let Some(file_path) = &self.with_config {
-            let file = File::open(&file_path);
-            let mut file = match file {
-                Ok(f) => f,
-                Err(_) => return Err(format!("Cannot open file: {}", file_path.display())),
+use crate::error::CassidyError;
+
+/// Reads whole content of given file into string
+pub(crate) fn read_file(file_path: &Path) -> Result<String, CassidyError> {
+    let io_error = |source| CassidyError::Io {
+        path: file_path.to_path_buf(),
+        source,
+    };
+    let mut file = File::open(file_path).map_err(io_error)?;
+    let mut data = String::new();
+    file.read_to_string(&mut data).map_err(io_error)?;
+    Ok(data)
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct LambdaPoint {
+    pub time: f64, // [h]
+    pub coef: f64,
+}
+
+/// Shape of arrival rate in time. Current lambda is equal to `lambda` multiplied by coefficient given by profile
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum LambdaProfile {
+    /// Coefficient is constant during each phase of cyclic lambda_coefs list
+    #[default]
+    Step,
+    /// Coefficient changes linearly from coefficient of one lambda_coefs phase to coefficient of the next one
+    Linear,
+    /// Coefficient equal to `mean + amplitude * cos(2 * pi * (t - peak) / period)`
+    Sinusoidal {
+        mean: f64,
+        amplitude: f64,
+        period: f64, // [h]
+        peak: f64,   // [h]
+    },
+    /// Daily profile read from CSV file with `hour,coef` lines, interpolated linearly
+    File {
+        path: PathBuf,
+        #[serde(skip)]
+        phases: Vec<LambdaPoint>, // converted to lambda_coefs-like phases starting at midnight
+    },
+}
+
+impl LambdaProfile {
+    pub fn validate(&self, lambda_coefs: &[LambdaPoint]) -> Result<(), CassidyError> {
+        match self {
+            LambdaProfile::File { .. } => Ok(()),
+            LambdaProfile::Step | LambdaProfile::Linear => {
+                if lambda_coefs.iter().map(|p| p.time).sum::<f64>() <= 0.0 {
+                    return Err(CassidyError::InvalidConfig(
+                        "lambda_coefs must have positive total time".to_owned(),
+                    ));
+                }
+                Ok(())
+            }
+            LambdaProfile::Sinusoidal {
+                mean,
+                amplitude,
+                period,
+                ..
+            } => {
+                if *amplitude < 0.0 || amplitude > mean || *period <= 0.0 {
+                    return Err(CassidyError::InvalidConfig(
+                        "lambda_profile amplitude must be from range [0, mean] and period must be greater than 0".to_owned(),
+                    ));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Reads daily profile from file
+    pub fn load(&mut self) -> Result<(), CassidyError> {
+        let LambdaProfile::File { path, phases } = self else {
+            return Ok(());
+        };
+        let data = read_file(path)?;
+        let invalid = |line: usize, msg: &str| {
+            CassidyError::InvalidConfig(format!(
+                "lambda_profile {} line {}: {}",
+                path.display(),
+                line + 1,
+                msg
+            ))
+        };
+        let mut points = Vec::new();
+        for (idx, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let values: Vec<f64> = line
+                .split(',')
+                .map(|v| v.trim().parse::<f64>())
+                .collect::<Result<_, _>>()
+                .map_err(|_| invalid(idx, "expected numbers in format hour,coef"))?;
+            let [hour, coef] = values[..] else {
+                return Err(invalid(idx, "expected numbers in format hour,coef"));
             };
-            let mut data = String::new();
-            let res = file.read_to_string(&mut data);
-            match res {
-                Err(_) => return Err(format!("Cannot read file: {}", file_path.display())),
-                _ => (),
+            if !(0.0..24.0).contains(&hour) || coef < 0.0 {
+                return Err(invalid(
+                    idx,
+                    "hour must be from range [0, 24) and coef must not be negative",
+                ));
+            }
+            points.push((hour, coef));
+        }
+        if points.is_empty() {
+            return Err(CassidyError::InvalidConfig(format!(
+                "lambda_profile {} has no points",
+                path.display()
+            )));
+        }
+        points.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        // coefficient at midnight is interpolated between the last and the first point
+        let (first, last) = (points[0], points[points.len() - 1]);
+        let wrap = 24.0 - last.0 + first.0;
+        let midnight = last.1 + (first.1 - last.1) * (24.0 - last.0) / wrap;
+        let mut knots = vec![(0.0, midnight)];
+        knots.extend(points);
+        knots.push((24.0, midnight));
+        *phases = knots
+            .windows(2)
+            .map(|w| LambdaPoint {
+                time: w[1].0 - w[0].0,
+                coef: w[0].1,
+            })
+            .collect();
+        Ok(())
+    }
+
+    /// Multiplies all times of profile by given factor, e.g. to convert them from hours to microseconds
+    pub fn scale_time(&mut self, factor: f64) {
+        match self {
+            LambdaProfile::Step | LambdaProfile::Linear => {}
+            LambdaProfile::Sinusoidal { period, peak, .. } => {
+                *period *= factor;
+                *peak *= factor;
+            }
+            LambdaProfile::File { phases, .. } => {
+                for phase in phases.iter_mut() {
+                    phase.time *= factor;
+                }
+            }
+        }
+    }
+
+    /// Returns lambda coefficient at given time.
+    /// Time must be given in the same units as times of profile and lambda_coefs
+    pub fn coef(&self, time: f64, lambda_coefs: &[LambdaPoint]) -> f64 {
+        match self {
+            LambdaProfile::Step => Self::phase_coef(lambda_coefs, time, false),
+            LambdaProfile::Linear => Self::phase_coef(lambda_coefs, time, true),
+            LambdaProfile::Sinusoidal {
+                mean,
+                amplitude,
+                period,
+                peak,
+            } => mean + amplitude * (2.0 * std::f64::consts::PI * (time - peak) / period).cos(),
+            LambdaProfile::File { phases, .. } => Self::phase_coef(phases, time, true),
+        }
+    }
+
+    /// Returns upper bound of lambda coefficient
+    pub fn max_coef(&self, lambda_coefs: &[LambdaPoint]) -> f64 {
+        let max = |phases: &[LambdaPoint]| phases.iter().map(|p| p.coef).fold(0.0, f64::max);
+        match self {
+            LambdaProfile::Step | LambdaProfile::Linear => max(lambda_coefs),
+            LambdaProfile::Sinusoidal {
+                mean, amplitude, ..
+            } => mean + amplitude,
+            LambdaProfile::File { phases, .. } => max(phases),
+        }
+    }
+
+    /// Coefficient of cyclic list of phases at given time.
+    /// If `interpolate` is set, coefficient changes linearly towards coefficient of the next phase.
+    fn phase_coef(phases: &[LambdaPoint], time: f64, interpolate: bool) -> f64 {
+        let period: f64 = phases.iter().map(|p| p.time).sum();
+        let mut t = time.rem_euclid(period);
+        for (idx, phase) in phases.iter().enumerate() {
+            if t < phase.time {
+                if !interpolate {
+                    return phase.coef;
+                }
+                let next = &phases[(idx + 1) % phases.len()];
+                return phase.coef + (next.coef - phase.coef) * t / phase.time;
+            }
+            t -= phase.time;
+        }
+        phases[phases.len() - 1].coef
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PowerPoint {
+    pub usage: f64, // [0-100]%
+    pub power: f64, // [W]
+}
+
+/// Power consumption of active station as function of its resource usage
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum PowerModel {
+    /// Flat active_power regardless of usage
+    #[default]
+    Constant,
+    /// EARTH linear model: p0 + delta_p * p_max * load, where load is usage from range [0-1]
+    Linear { p0: f64, delta_p: f64, p_max: f64 }, // [W], [-], [W]
+    /// Linear interpolation between (usage, power) points, sorted by usage.
+    /// Outside of given points power is constant.
+    Piecewise { points: Vec<PowerPoint> },
+}
+
+impl PowerModel {
+    /// Returns power [W] of active station with given usage [0-100]%
+    pub fn power(&self, active_power: f64, usage: f64) -> f64 {
+        match self {
+            PowerModel::Constant => active_power,
+            PowerModel::Linear { p0, delta_p, p_max } => p0 + delta_p * p_max * usage / 100.0,
+            PowerModel::Piecewise { points } => {
+                let idx = points.partition_point(|p| p.usage < usage);
+                if idx == 0 {
+                    return points[0].power;
+                }
+                if idx == points.len() {
+                    return points[idx - 1].power;
+                }
+                let (a, b) = (&points[idx - 1], &points[idx]);
+                a.power + (b.power - a.power) * (usage - a.usage) / (b.usage - a.usage)
+            }
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), CassidyError> {
+        match self {
+            PowerModel::Constant => (),
+            PowerModel::Linear { p0, delta_p, p_max } => {
+                if *p0 < 0.0 || *delta_p < 0.0 || *p_max < 0.0 {
+                    return Err(CassidyError::InvalidConfig(
+                        "power_model parameters must be greater than 0".to_owned(),
+                    ));
+                }
+            }
+            PowerModel::Piecewise { points } => {
+                if points.is_empty() {
+                    return Err(CassidyError::InvalidConfig(
+                        "power_model points list must not be empty".to_owned(),
+                    ));
+                }
+                for p in points.iter() {
+                    if p.usage < 0.0 || p.usage > 100.0 || p.power < 0.0 {
+                        return Err(CassidyError::InvalidConfig("power_model point must have usage from range [0-100]% and power greater than 0".to_owned()));
+                    }
+                }
+                if points.windows(2).any(|w| w[0].usage >= w[1].usage) {
+                    return Err(CassidyError::InvalidConfig(
+                        "power_model points must be sorted by usage".to_owned(),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransitionPoint {
+    pub progress: f64, // [0-100]% of transition time
+    pub power: f64,    // [W]
+}
+
+/// Power draw of station during transition between sleep and active mode,
+/// integrated over duration of the transition
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum TransitionPower {
+    /// Flat wakeup_power during whole transition
+    #[default]
+    Constant,
+    /// Linear ramp from power of the left state to power of the entered state.
+    /// Active station is considered to be without load
+    Ramp,
+    /// Linear interpolation between (progress, power) points, sorted by progress.
+    /// Outside of given points power is constant.
+    Piecewise { points: Vec<TransitionPoint> },
+}
+
+impl TransitionPower {
+    /// Returns average power [W] between given progress points from range [0-1] of transition,
+    /// which starts in state drawing `powers.0` and ends in state drawing `powers.1`
+    pub fn mean_power(&self, from: f64, to: f64, powers: (f64, f64), wakeup_power: f64) -> f64 {
+        let points: Vec<(f64, f64)> = match self {
+            TransitionPower::Constant => return wakeup_power,
+            TransitionPower::Ramp => vec![(0.0, powers.0), (1.0, powers.1)],
+            TransitionPower::Piecewise { points } => points
+                .iter()
+                .map(|p| (p.progress / 100.0, p.power))
+                .collect(),
+        };
+        let power = |x: f64| {
+            let idx = points.partition_point(|p| p.0 < x);
+            if idx == 0 {
+                return points[0].1;
+            }
+            if idx == points.len() {
+                return points[idx - 1].1;
+            }
+            let (a, b) = (points[idx - 1], points[idx]);
+            a.1 + (b.1 - a.1) * (x - a.0) / (b.0 - a.0)
+        };
+        if to <= from {
+            return power(from);
+        }
+        // power is linear between breakpoints, so trapezoids give exact integral
+        let mut xs = vec![from];
+        xs.extend(points.iter().map(|p| p.0).filter(|x| *x > from && *x < to));
+        xs.push(to);
+        let energy: f64 = xs
+            .windows(2)
+            .map(|w| (power(w[0]) + power(w[1])) / 2.0 * (w[1] - w[0]))
+            .sum();
+        energy / (to - from)
+    }
+
+    pub fn validate(&self) -> Result<(), CassidyError> {
+        if let TransitionPower::Piecewise { points } = self {
+            if points.is_empty() {
+                return Err(CassidyError::InvalidConfig(
+                    "transition_power points list must not be empty".to_owned(),
+                ));
+            }
+            for p in points.iter() {
+                if p.progress < 0.0 || p.progress > 100.0 || p.power < 0.0 {
+                    return Err(CassidyError::InvalidConfig("transition_power point must have progress from range [0-100]% and power greater than 0".to_owned()));
+                }
+            }
+            if points.windows(2).any(|w| w[0].progress >= w[1].progress) {
+                return Err(CassidyError::InvalidConfig(
+                    "transition_power points must be sorted by progress".to_owned(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Admission control of new arrivals at their own station.
+/// Redirected, handed over and queued users can always use all free resource blocks
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum AdmissionControl {
+    /// New arrivals are admitted while they fit into free resources
+    #[default]
+    Greedy,
+    /// Given number of resource blocks is reserved for redirected and handover users
+    GuardChannel { reserved: usize },
+    /// Number of resource blocks, which must stay free after admitting new arrival,
+    /// for every priority level. Levels above the list have no reservation
+    TrunkReservation { thresholds: Vec<usize> },
+}
+
+impl AdmissionControl {
+    /// Returns number of resource blocks, which new arrival with given priority must leave free
+    pub fn reserved(&self, priority: usize) -> usize {
+        match self {
+            AdmissionControl::Greedy => 0,
+            AdmissionControl::GuardChannel { reserved } => *reserved,
+            AdmissionControl::TrunkReservation { thresholds } => {
+                thresholds.get(priority).copied().unwrap_or(0)
+            }
+        }
+    }
+
+    pub fn validate(&self, resources_count: usize) -> Result<(), CassidyError> {
+        let reserved = match self {
+            AdmissionControl::Greedy => 0,
+            AdmissionControl::GuardChannel { reserved } => *reserved,
+            AdmissionControl::TrunkReservation { thresholds } => {
+                thresholds.iter().copied().max().unwrap_or(0)
+            }
+        };
+        if reserved >= resources_count {
+            return Err(CassidyError::InvalidConfig(
+                "admission reserved blocks must be lower than resources_count".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Weight of single resource blocks count in [`BlocksDistribution::Weighted`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlocksWeight {
+    pub blocks: usize,
+    pub weight: f64,
+}
+
+/// Distribution of number of resource blocks requested by single user
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum BlocksDistribution {
+    /// Every user requests the same number of blocks
+    Constant { count: usize },
+    /// Number of blocks drawn uniformly from range <min, max>
+    Uniform { min: usize, max: usize },
+    /// Number of blocks drawn from list with given weights
+    Weighted { points: Vec<BlocksWeight> },
+}
+
+impl Default for BlocksDistribution {
+    fn default() -> Self {
+        BlocksDistribution::Constant { count: 1 }
+    }
+}
+
+impl BlocksDistribution {
+    /// Largest number of blocks that can be requested
+    pub fn max_blocks(&self) -> usize {
+        match self {
+            BlocksDistribution::Constant { count } => *count,
+            BlocksDistribution::Uniform { max, .. } => *max,
+            BlocksDistribution::Weighted { points } => {
+                points.iter().map(|p| p.blocks).max().unwrap_or(0)
+            }
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), CassidyError> {
+        match self {
+            BlocksDistribution::Constant { count } => {
+                if *count == 0 {
+                    return Err(CassidyError::InvalidConfig(
+                        "blocks count must be greater than 0".to_owned(),
+                    ));
+                }
             }
-            match toml::from_str::<Config>(&data) {
-                Ok(c) => Ok(c),
-                Err(e) => Err(e.to_string()),
+            BlocksDistribution::Uniform { min, max } => {
+                if *min == 0 || max < min {
+                    return Err(CassidyError::InvalidConfig(
+                        "blocks range must be not empty and greater than 0".to_owned(),
+                    ));
+                }
+            }
+            BlocksDistribution::Weighted { points } => {
+                if points.is_empty() {
+                    return Err(CassidyError::InvalidConfig(
+                        "blocks points list must not be empty".to_owned(),
+                    ));
+                }
+                if points.iter().any(|p| p.blocks == 0 || p.weight <= 0.0) {
+                    return Err(CassidyError::InvalidConfig(
+                        "blocks points must have blocks and weight greater than 0".to_owned(),
+                    ));
+                }
             }
-        } else {
-            Ok(Config::default())
         }
+        Ok(())
     }
+}
 
-    pub fn create_walk_over_config(&self) -> Result<Option<WalkOverConfig>, String> {
-        if let Some(file_path) = &self.walk_over {
-            let file = File::open(&file_path);
-            let mut file = match file {
-                Ok(f) => f,
-                Err(_) => return Err(format!("Cannot open file: {}", file_path.display())),
+/// Single bin of empirical holding time histogram
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistogramBin {
+    pub lower: f64, // [ms]
+    pub upper: f64, // [ms]
+    pub weight: f64,
+}
+
+/// Distribution of user's holding (processing) time. All times are given in miliseconds
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ServiceTime {
+    /// Uniform distribution on range <process_time_min, process_time_max>
+    #[default]
+    Uniform,
+    Exponential {
+        mean: f64,
+    },
+    /// Log-normal distribution with given mean and standard deviation of holding time
+    LogNormal {
+        mean: f64,
+        std_dev: f64,
+    },
+    /// Pareto distribution with given minimal value (scale) and shape (tail index)
+    Pareto {
+        scale: f64,
+        shape: f64,
+    },
+    Deterministic {
+        time: f64,
+    },
+    /// Histogram read from CSV file with `lower,upper,weight` lines.
+    /// Bin is chosen according to weights and time is drawn uniformly inside the bin
+    Empirical {
+        path: PathBuf,
+        #[serde(skip)]
+        bins: Vec<HistogramBin>,
+    },
+}
+
+impl ServiceTime {
+    pub fn validate(&self) -> Result<(), CassidyError> {
+        let valid = match self {
+            ServiceTime::Uniform | ServiceTime::Empirical { .. } => true,
+            ServiceTime::Exponential { mean } => *mean > 0.0,
+            ServiceTime::LogNormal { mean, std_dev } => *mean > 0.0 && *std_dev > 0.0,
+            ServiceTime::Pareto { scale, shape } => *scale > 0.0 && *shape > 0.0,
+            ServiceTime::Deterministic { time } => *time >= 0.0,
+        };
+        if !valid {
+            return Err(CassidyError::InvalidConfig(
+                "service_time parameters must be greater than 0".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Reads histogram of empirical distribution from its file. Other distributions are left unchanged
+    pub fn load(&mut self) -> Result<(), CassidyError> {
+        let ServiceTime::Empirical { path, bins } = self else {
+            return Ok(());
+        };
+        let data = read_file(path)?;
+        let invalid = |line: usize, msg: &str| {
+            CassidyError::InvalidConfig(format!(
+                "service_time histogram {} line {}: {}",
+                path.display(),
+                line + 1,
+                msg
+            ))
+        };
+        bins.clear();
+        for (idx, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let values: Vec<f64> = line
+                .split(',')
+                .map(|v| v.trim().parse::<f64>())
+                .collect::<Result<_, _>>()
+                .map_err(|_| invalid(idx, "expected numbers in format lower,upper,weight"))?;
+            let [lower, upper, weight] = values[..] else {
+                return Err(invalid(
+                    idx,
+                    "expected numbers in format lower,upper,weight",
+                ));
             };
-            let mut data = String::new();
-            let res = file.read_to_string(&mut data);
-            match res {
-                Err(_) => return Err(format!("Cannot read file: {}", file_path.display())),
-                _ => (),
+            if lower < 0.0 || upper < lower || weight < 0.0 {
+                return Err(invalid(
+                    idx,
+                    "bin must have 0 <= lower <= upper and weight greater than 0",
+                ));
             }
-            match toml::from_str::<WalkOverConfig>(&data) {
-                Ok(c) => Ok(Some(c)),
-                Err(e) => Err(e.to_string()),
+            bins.push(HistogramBin {
+                lower,
+                upper,
+                weight,
+            });
+        }
+        if bins.iter().map(|b| b.weight).sum::<f64>() <= 0.0 {
+            return Err(CassidyError::InvalidConfig(format!(
+                "service_time histogram {} must contain bins with positive weight",
+                path.display()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Group of users with common arrival share, holding time and resource demand
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServiceClass {
+    pub name: String,
+    /// Relative share of arrivals. Shares of all classes are normalized
+    pub share: f64,
+    /// Overrides of global process time range
+    pub process_time_min: Option<u64>, // [ms]
+    pub process_time_max: Option<u64>, // [ms]
+    /// Override of global holding time distribution
+    pub service_time: Option<ServiceTime>,
+    #[serde(default)]
+    pub blocks: BlocksDistribution,
+    /// Users of class with higher priority are admitted first from priority queue.
+    /// Ignored when priority levels are configured
+    #[serde(default)]
+    pub priority: usize,
+}
+
+impl ServiceClass {
+    pub fn validate(&self, cfg: &Config) -> Result<(), CassidyError> {
+        // name is used in headers of CSV results
+        if self.name.is_empty() || self.name.contains([',', '"', '\n', '\r']) {
+            return Err(CassidyError::InvalidConfig(
+                "name must not be empty and must not contain commas, quotes or newlines".to_owned(),
+            ));
+        }
+        if self.share <= 0.0 {
+            return Err(CassidyError::InvalidConfig(
+                "share must be greater than 0".to_owned(),
+            ));
+        }
+        let min = self.process_time_min.unwrap_or(cfg.process_time_min);
+        let max = self.process_time_max.unwrap_or(cfg.process_time_max);
+        if min > max {
+            return Err(CassidyError::InvalidConfig(
+                "process_time_min must not be greater than process_time_max".to_owned(),
+            ));
+        }
+        if let Some(service_time) = &self.service_time {
+            service_time.validate()?;
+        }
+        self.blocks.validate()?;
+        let largest_station = (0..cfg.stations_count)
+            .map(|idx| cfg.station_params(idx).resources_count)
+            .max()
+            .unwrap_or(0);
+        if self.blocks.max_blocks() > largest_station {
+            return Err(CassidyError::InvalidConfig(
+                "blocks must not exceed resources_count of the largest station".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Sleep mode of station. Deeper levels usually save more power, but take longer to wake up from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SleepLevel {
+    pub name: String,
+    pub power: f64,        // [W]
+    pub wakeup_delay: u64, // [ms]
+    /// Time spent in previous level, after which sleeping station is demoted to this level.
+    /// Not used by the first level, which is entered when station is put to sleep
+    #[serde(default)]
+    pub idle_time: u64, // [ms]
+}
+
+impl SleepLevel {
+    pub fn validate(&self) -> Result<(), CassidyError> {
+        if self.power < 0.0 {
+            return Err(CassidyError::InvalidConfig(format!(
+                "sleep level {}: power must be greater than 0",
+                self.name
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Overrides of global station parameters for single station.
+/// Fields that are not given are taken from global config.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StationConfig {
+    pub resources_count: Option<usize>,
+    pub active_power: Option<f64>,     // [W]
+    pub sleep_power: Option<f64>,      // [W]
+    pub wakeup_power: Option<f64>,     // [W]
+    pub sleep_threshold: Option<f64>,  // [0-100]%
+    pub wakeup_threshold: Option<f64>, // [0-100]%
+    pub lambda_weight: Option<f64>,
+    pub phase_offset: Option<f64>, // [h]
+    pub lambda_profile: Option<LambdaProfile>,
+    pub power_model: Option<PowerModel>,
+    pub transition_power: Option<TransitionPower>,
+    pub arrival_process: Option<ArrivalProcessKind>,
+    pub admission: Option<AdmissionControl>,
+}
+
+/// Parameters of single station, resolved from global config and station overrides
+#[derive(Debug, Clone, PartialEq)]
+pub struct StationParams {
+    pub resources_count: usize,
+    pub active_power: f64,     // [W]
+    pub sleep_power: f64,      // [W]
+    pub wakeup_power: f64,     // [W]
+    pub sleep_threshold: f64,  // [0-100]%
+    pub wakeup_threshold: f64, // [0-100]%
+    pub lambda_weight: f64,
+    pub phase_offset: f64, // [h]
+    pub lambda_profile: LambdaProfile,
+    pub power_model: PowerModel,
+    pub transition_power: TransitionPower,
+    pub arrival_process: ArrivalProcessKind,
+    pub admission: AdmissionControl,
+    /// Configured sleep levels, or single level with sleep_power and wakeup_delay
+    pub sleep_levels: Vec<SleepLevel>,
+}
+
+/// Single state of Markov-modulated Poisson process
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MmppState {
+    pub rate: f64,      // relative arrival rate in this state
+    pub mean_time: f64, // [ms] mean time spent in this state
+}
+
+/// Process generating arrivals of new users in station.
+/// Every process keeps long-term average arrival rate equal to current lambda,
+/// so they differ only in burstiness of traffic.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ArrivalProcessKind {
+    /// Exponential inter-arrival times
+    #[default]
+    Poisson,
+    /// Poisson process with rate switching between given states. Time spent in each state
+    /// is exponential and next state is chosen uniformly from remaining ones.
+    /// Rates are normalized, so that average rate over time equals lambda.
+    Mmpp { states: Vec<MmppState> },
+    /// Groups of users arriving at the same time. Groups arrive as Poisson process
+    /// and group size is uniform on range <min_size, max_size>
+    Batch { min_size: usize, max_size: usize },
+    /// Constant inter-arrival time equal to 1 / lambda
+    Deterministic,
+    /// Renewal process with heavy-tailed Pareto inter-arrival times with given shape
+    Pareto { shape: f64 },
+}
+
+impl ArrivalProcessKind {
+    pub fn validate(&self) -> Result<(), CassidyError> {
+        match self {
+            ArrivalProcessKind::Poisson | ArrivalProcessKind::Deterministic => Ok(()),
+            ArrivalProcessKind::Mmpp { states } => {
+                if states.len() < 2 {
+                    return Err(CassidyError::InvalidConfig(
+                        "arrival_process must have at least 2 states".to_owned(),
+                    ));
+                }
+                if states.iter().any(|s| s.rate < 0.0 || s.mean_time <= 0.0) {
+                    return Err(CassidyError::InvalidConfig(
+                        "arrival_process state rate must not be negative and mean_time must be greater than 0".to_owned(),
+                    ));
+                }
+                if states.iter().all(|s| s.rate == 0.0) {
+                    return Err(CassidyError::InvalidConfig(
+                        "arrival_process must have at least one state with rate greater than 0"
+                            .to_owned(),
+                    ));
+                }
+                Ok(())
+            }
+            ArrivalProcessKind::Batch { min_size, max_size } => {
+                if *min_size == 0 || min_size > max_size {
+                    return Err(CassidyError::InvalidConfig(
+                        "arrival_process batch size must be from range [1, max_size]".to_owned(),
+                    ));
+                }
+                Ok(())
+            }
+            ArrivalProcessKind::Pareto { shape } => {
+                // mean inter-arrival time is infinite for shape <= 1
+                if *shape <= 1.0 {
+                    return Err(CassidyError::InvalidConfig(
+                        "arrival_process shape must be greater than 1".to_owned(),
+                    ));
+                }
+                Ok(())
             }
-        } else {
-            Ok(None)
         }
     }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    pub x: f64, // [m]
+    pub y: f64, // [m]
+}
 
-    pub fn validate(self) -> Result<Cli, String> {
-        if self.duration < 0.0 {
-            return Err("Duration must be greater than 0".to_string());
+/// Neighbourhood of stations. Users can be redirected or offloaded only between neighbouring stations
+/// and overloaded station can wake up only its neighbours.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Topology {
+    /// Every station is neighbour of every other station
+    #[default]
+    Full,
+    /// Explicit list of neighbours of every station. Links are symmetric
+    Adjacency { neighbours: Vec<Vec<usize>> },
+    /// Stations placed at given positions, which are neighbours if their coverage areas overlap
+    Coverage {
+        positions: Vec<Position>,
+        radius: f64, // [m]
+        #[serde(skip)]
+        neighbours: Vec<Vec<usize>>,
+    },
+}
+
+impl Topology {
+    pub fn validate(&self, stations_count: usize) -> Result<(), CassidyError> {
+        match self {
+            Topology::Full => Ok(()),
+            Topology::Adjacency { neighbours } => {
+                if neighbours.len() != stations_count {
+                    return Err(CassidyError::InvalidConfig(
+                        "topology must have list of neighbours for every station".to_owned(),
+                    ));
+                }
+                if neighbours
+                    .iter()
+                    .flatten()
+                    .any(|idx| *idx >= stations_count)
+                {
+                    return Err(CassidyError::InvalidConfig(
+                        "topology neighbour must be from range [0, stations_count)".to_owned(),
+                    ));
+                }
+                Ok(())
+            }
+            Topology::Coverage {
+                positions, radius, ..
+            } => {
+                if positions.len() != stations_count {
+                    return Err(CassidyError::InvalidConfig(
+                        "topology must have position of every station".to_owned(),
+                    ));
+                }
+                if *radius <= 0.0 {
+                    return Err(CassidyError::InvalidConfig(
+                        "topology radius must be greater than 0".to_owned(),
+                    ));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Builds sorted and symmetric lists of neighbours
+    pub fn prepare(&mut self) {
+        match self {
+            Topology::Full => {}
+            Topology::Adjacency { neighbours } => {
+                let mut lists = vec![Vec::new(); neighbours.len()];
+                for (a, list) in neighbours.iter().enumerate() {
+                    for b in list.iter().filter(|b| **b != a) {
+                        lists[a].push(*b);
+                        lists[*b].push(a);
+                    }
+                }
+                *neighbours = lists;
+            }
+            Topology::Coverage {
+                positions,
+                radius,
+                neighbours,
+            } => {
+                let mut lists = vec![Vec::new(); positions.len()];
+                for (a, pa) in positions.iter().enumerate() {
+                    for (b, pb) in positions.iter().enumerate().skip(a + 1) {
+                        if (pa.x - pb.x).hypot(pa.y - pb.y) <= 2.0 * *radius {
+                            lists[a].push(b);
+                            lists[b].push(a);
+                        }
+                    }
+                }
+                *neighbours = lists;
+            }
+        }
+        if let Topology::Adjacency { neighbours } | Topology::Coverage { neighbours, .. } = self {
+            for list in neighbours.iter_mut() {
+                list.sort_unstable();
+                list.dedup();
+            }
+        }
+    }
+
+    /// Returns true if station `to` can take over users of station `from`.
+    /// In full topology this is true for every pair, including station itself.
+    pub fn is_neighbour(&self, from: usize, to: usize) -> bool {
+        match self {
+            Topology::Full => true,
+            Topology::Adjacency { neighbours } | Topology::Coverage { neighbours, .. } => {
+                neighbours[from].binary_search(&to).is_ok()
+            }
+        }
+    }
+
+    /// Returns positions of stations and their coverage radius, if topology has them
+    pub fn coverage(&self) -> Option<(&[Position], f64)> {
+        match self {
+            Topology::Coverage {
+                positions, radius, ..
+            } => Some((positions, *radius)),
+            _ => None,
         }
-        if let Some(path) = &self.with_config {
-            if let Some(file_dir) = path.parent() {
-                if !file_dir.exists() {
-                    return Err(format!(
-                        "Given path to config file: '{}' does not exists",
-                        &path.display()
+    }
+}
+
+/// Cluster of users placed around its center with normal distribution
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hotspot {
+    pub x: f64,       // [m]
+    pub y: f64,       // [m]
+    pub std_dev: f64, // [m]
+    /// Relative share of users spawned around this hotspot
+    pub weight: f64,
+}
+
+/// Distribution of positions of new users in spatial mode
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum UserPlacement {
+    /// Uniform over whole area
+    #[default]
+    Uniform,
+    /// Users spawned around hotspots. Share of users given by background weight is placed uniformly
+    Hotspots {
+        hotspots: Vec<Hotspot>,
+        #[serde(default)]
+        background: f64,
+    },
+    /// Grid of relative densities read from CSV file, where each line is a row of cells.
+    /// Grid covers whole area, first line is at y = 0. Position is uniform inside chosen cell
+    DensityMap {
+        path: PathBuf,
+        #[serde(skip)]
+        cells: Vec<Vec<f64>>,
+    },
+}
+
+impl UserPlacement {
+    pub fn validate(&self) -> Result<(), CassidyError> {
+        let UserPlacement::Hotspots {
+            hotspots,
+            background,
+        } = self
+        else {
+            return Ok(());
+        };
+        if *background < 0.0 || hotspots.iter().any(|h| h.weight < 0.0 || h.std_dev < 0.0) {
+            return Err(CassidyError::InvalidConfig(
+                "spatial hotspot weight, std_dev and background must not be negative".to_owned(),
+            ));
+        }
+        if background + hotspots.iter().map(|h| h.weight).sum::<f64>() <= 0.0 {
+            return Err(CassidyError::InvalidConfig(
+                "spatial hotspots must have positive total weight".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Reads grid of density map from its file. Other placements are left unchanged
+    pub fn load(&mut self) -> Result<(), CassidyError> {
+        let UserPlacement::DensityMap { path, cells } = self else {
+            return Ok(());
+        };
+        let data = read_file(path)?;
+        let invalid = |line: usize, msg: &str| {
+            CassidyError::InvalidConfig(format!(
+                "density map {} line {}: {}",
+                path.display(),
+                line + 1,
+                msg
+            ))
+        };
+        cells.clear();
+        for (idx, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let row: Vec<f64> = line
+                .split(',')
+                .map(|v| v.trim().parse::<f64>())
+                .collect::<Result<_, _>>()
+                .map_err(|_| invalid(idx, "expected comma separated numbers"))?;
+            if row.iter().any(|w| *w < 0.0) {
+                return Err(invalid(idx, "density must not be negative"));
+            }
+            if cells.first().is_some_and(|first| first.len() != row.len()) {
+                return Err(invalid(idx, "all rows must have the same length"));
+            }
+            cells.push(row);
+        }
+        if cells.iter().flatten().sum::<f64>() <= 0.0 {
+            return Err(CassidyError::InvalidConfig(format!(
+                "density map {} must contain cells with positive density",
+                path.display()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Movement of users in spatial mode.
+/// Moving users travel with random speed from range <speed_min, speed_max> [m/s]
+/// to the end of their leg and then wait for pause [ms] before starting the next leg
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum MobilityModel {
+    /// Users stay at their initial position
+    #[default]
+    Static,
+    /// Each leg ends at random point of area
+    RandomWaypoint {
+        speed_min: f64,
+        speed_max: f64,
+        pause: u64,
+    },
+    /// Each leg goes in random direction until border of area
+    RandomDirection {
+        speed_min: f64,
+        speed_max: f64,
+        pause: u64,
+    },
+}
+
+impl MobilityModel {
+    pub fn validate(&self) -> Result<(), CassidyError> {
+        match self {
+            MobilityModel::Static => Ok(()),
+            MobilityModel::RandomWaypoint {
+                speed_min,
+                speed_max,
+                ..
+            }
+            | MobilityModel::RandomDirection {
+                speed_min,
+                speed_max,
+                ..
+            } => {
+                if *speed_min <= 0.0 || speed_max < speed_min {
+                    return Err(CassidyError::InvalidConfig(
+                        "mobility speed must be from range (0, speed_max]".to_owned(),
                     ));
                 }
+                Ok(())
             }
         }
-        if self.iterations == 0 {
-            return Err("Iterations must be greater than 0".to_owned());
+    }
+}
+
+/// Spatial mode, where users spawn at random positions in rectangular area <0, width> x <0, height>
+/// and attach to the nearest awake station covering their position.
+/// Stations positions and coverage radius are taken from coverage topology
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpatialConfig {
+    pub width: f64,  // [m]
+    pub height: f64, // [m]
+    pub placement: UserPlacement,
+    pub mobility: MobilityModel,
+    /// Period of updating positions of moving users and checking their handovers
+    pub handover_interval: u64, // [ms]
+}
+
+impl Default for SpatialConfig {
+    fn default() -> Self {
+        Self {
+            width: 1000.0,
+            height: 1000.0,
+            placement: UserPlacement::default(),
+            mobility: MobilityModel::default(),
+            handover_interval: 1000,
         }
-        Ok(self)
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct LambdaPoint {
-    pub time: f64, // [h]
-    pub coef: f64,
+impl SpatialConfig {
+    pub fn validate(&self) -> Result<(), CassidyError> {
+        if self.width <= 0.0 || self.height <= 0.0 {
+            return Err(CassidyError::InvalidConfig(
+                "spatial width and height must be greater than 0".to_owned(),
+            ));
+        }
+        if self.handover_interval == 0 {
+            return Err(CassidyError::InvalidConfig(
+                "spatial handover_interval must be greater than 0".to_owned(),
+            ));
+        }
+        self.placement.validate()?;
+        self.mobility.validate()
+    }
+
+    /// Returns true if users move
+    pub fn is_mobile(&self) -> bool {
+        self.mobility != MobilityModel::Static
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Where users wait for free resources
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QueueScope {
+    /// Single queue for the whole network. Users are admitted by any active station
+    #[default]
+    Global,
+    /// Separate queue of every station. Users are admitted only by station they arrived at
+    Station,
+}
+
+/// Order, in which waiting users are admitted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QueueDiscipline {
+    #[default]
+    Fifo,
+    /// Users with higher priority first, FIFO within the same priority
+    Priority,
+}
+
+/// Waiting queue of users, which could not be served by any station.
+/// Waiting users are admitted when resources are freed, or abandon the queue after their patience expires
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QueueConfig {
+    pub scope: QueueScope,
+    /// Maximal number of waiting users in single queue. Users arriving at full queue are dropped
+    pub max_length: usize,
+    pub discipline: QueueDiscipline,
+    /// Distribution of time [ms], after which waiting user abandons the queue.
+    /// Users wait until admission if it is not given
+    pub patience: Option<ServiceTime>,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            scope: QueueScope::default(),
+            max_length: 100,
+            discipline: QueueDiscipline::default(),
+            patience: None,
+        }
+    }
+}
+
+impl QueueConfig {
+    pub fn validate(&self) -> Result<(), CassidyError> {
+        if self.max_length == 0 {
+            return Err(CassidyError::InvalidConfig(
+                "queue max_length must be greater than 0".to_owned(),
+            ));
+        }
+        match &self.patience {
+            // uniform distribution is defined only by process time range
+            Some(ServiceTime::Uniform) => Err(CassidyError::InvalidConfig(
+                "queue patience must not be Uniform".to_owned(),
+            )),
+            Some(patience) => patience.validate().map_err(|_| {
+                CassidyError::InvalidConfig(
+                    "queue patience parameters must be greater than 0".to_owned(),
+                )
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Priority levels of users. Level of every arriving user is drawn from relative shares,
+/// level with higher index has higher priority
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PriorityConfig {
+    pub shares: Vec<f64>,
+    /// Arriving user, which does not fit into its full station, takes resources
+    /// of the active user with the lowest priority lower than its own
+    pub preemption: bool,
+}
+
+impl Default for PriorityConfig {
+    fn default() -> Self {
+        Self {
+            shares: vec![1.0],
+            preemption: true,
+        }
+    }
+}
+
+impl PriorityConfig {
+    pub fn validate(&self) -> Result<(), CassidyError> {
+        if self.shares.is_empty() {
+            return Err(CassidyError::InvalidConfig(
+                "priorities shares must not be empty".to_owned(),
+            ));
+        }
+        if self.shares.iter().any(|share| *share <= 0.0) {
+            return Err(CassidyError::InvalidConfig(
+                "priorities shares must be greater than 0".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Strategy of choosing station for user that could not be served by its own station
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RedirectPolicyKind {
+    /// Active station with lowest usage
+    #[default]
+    LeastLoaded,
+    /// Next active station with free resources, in cyclic order
+    RoundRobin,
+    /// Random active station with free resources
+    Random,
+    /// Active station with free resources and lowest id
+    FirstFit,
+    /// Least loaded of two adjacent stations (id - 1 and id + 1)
+    Neighbour,
+    /// Users are never redirected
+    NoRedirect,
+}
+
+/// Energy-saving strategy used when sleep state logic is enabled
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum SleepControllerKind {
+    /// Wake up station when any station exceeds wakeup_threshold,
+    /// put station to sleep when it is below sleep_threshold
+    #[default]
+    Threshold,
+    /// Threshold rule, where thresholds must be exceeded for at least hold_time
+    Hysteresis { hold_time: u64 }, // [ms]
+    /// Threshold rule, which always keeps at least given number of stations awake
+    MinAwake { count: usize },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Config {
     pub process_time_max: u64, // [ms]
     pub process_time_min: u64, // [ms]
     pub lambda: f64,           // [users per second]
     pub lambda_coefs: Vec<LambdaPoint>,
+    pub lambda_profile: LambdaProfile,
     pub resources_count: usize,
     pub sleep_threshold: f64,  // [0-100]%
     pub wakeup_threshold: f64, // [0-100]%
@@ -133,7 +1238,28 @@ pub struct Config {
     pub sleep_power: f64,  // [W]
     pub wakeup_power: f64, // [W]
     pub wakeup_delay: u64, // [ms]
+    pub sleep_levels: Vec<SleepLevel>,
     pub log_buffer: usize,
+    pub warmup: f64, // [h]
+    pub auto_warmup: bool,
+    pub redirect_policy: RedirectPolicyKind,
+    pub sleep_controller: SleepControllerKind,
+    pub stations: Vec<StationConfig>,
+    pub power_model: PowerModel,
+    pub transition_power: TransitionPower,
+    pub arrival_process: ArrivalProcessKind,
+    pub admission: AdmissionControl,
+    pub service_time: ServiceTime,
+    pub service_classes: Vec<ServiceClass>,
+    pub trace: Option<PathBuf>,
+    pub topology: Topology,
+    pub spatial: Option<SpatialConfig>,
+    pub queue: Option<QueueConfig>,
+    pub priorities: Option<PriorityConfig>,
+    /// Price of energy [per kWh] and carbon intensity [g CO2 per kWh] by hour of simulated day.
+    /// Single value applies to the whole day, otherwise there must be value for each of 24 hours
+    pub tariff: Option<Vec<f64>>,
+    pub carbon_intensity: Option<Vec<f64>>,
 }
 
 impl Default for Config {
@@ -160,6 +1286,7 @@ impl Default for Config {
                     time: 6.0,
                 },
             ],
+            lambda_profile: LambdaProfile::default(),
             resources_count: 273,
             sleep_threshold: 20.0,
             wakeup_threshold: 80.0,
@@ -168,58 +1295,384 @@ impl Default for Config {
             sleep_power: 1.0,
             wakeup_power: 1000.0,
             wakeup_delay: 50,
+            sleep_levels: Vec::new(),
             log_buffer: 1000,
+            warmup: 0.0,
+            auto_warmup: false,
+            redirect_policy: RedirectPolicyKind::default(),
+            sleep_controller: SleepControllerKind::default(),
+            stations: Vec::new(),
+            power_model: PowerModel::default(),
+            transition_power: TransitionPower::default(),
+            arrival_process: ArrivalProcessKind::default(),
+            admission: AdmissionControl::default(),
+            service_time: ServiceTime::default(),
+            service_classes: Vec::new(),
+            trace: None,
+            topology: Topology::default(),
+            spatial: None,
+            queue: None,
+            priorities: None,
+            tariff: None,
+            carbon_intensity: None,
         }
     }
 }
 
+impl StationParams {
+    /// Arrival rate of station at given time [us], following its lambda profile
+    /// delayed by phase offset and scaled by lambda weight
+    pub fn lambda_at(&self, time: u64, cfg: &Config) -> f64 {
+        // positive offset delays profile of station
+        let offset = self.phase_offset * 3600.0 * 1_000_000.0;
+        cfg.lambda
+            * self.lambda_weight
+            * self
+                .lambda_profile
+                .coef(time as f64 - offset, &cfg.lambda_coefs)
+    }
+
+    pub fn validate(&self) -> Result<(), CassidyError> {
+        if self.resources_count == 0 {
+            return Err(CassidyError::InvalidConfig(
+                "resources_count must be greater than 0".to_owned(),
+            ));
+        }
+        if self.sleep_threshold > 100.0 {
+            return Err(CassidyError::InvalidConfig(
+                "sleep_threshold must be from range [0-100]%".to_owned(),
+            ));
+        }
+        if self.wakeup_threshold > 100.0 || self.wakeup_threshold < self.sleep_threshold {
+            return Err(CassidyError::InvalidConfig("wakeup_threshold must be from range [0-100]% and must be greater than sleep_threshold".to_owned()));
+        }
+        if self.sleep_threshold >= self.wakeup_threshold / 2.0 {
+            println!("Warning] sleep_threshold: {} is greater than wakeup_threshold / 2: {}. This can cause oscillations in stations state", self.sleep_threshold, self.wakeup_threshold)
+        }
+        if self.active_power < 0.0 {
+            return Err(CassidyError::InvalidConfig(
+                "active_power must be greater than 0".to_owned(),
+            ));
+        }
+        if self.sleep_power < 0.0 {
+            return Err(CassidyError::InvalidConfig(
+                "sleep_power must be greater than 0".to_owned(),
+            ));
+        }
+        if self.wakeup_power < 0.0 {
+            return Err(CassidyError::InvalidConfig(
+                "wakeup_power must be greater than 0".to_owned(),
+            ));
+        }
+        if self.lambda_weight < 0.0 {
+            return Err(CassidyError::InvalidConfig(
+                "lambda_weight must not be negative".to_owned(),
+            ));
+        }
+        self.power_model.validate()?;
+        self.transition_power.validate()?;
+        self.arrival_process.validate()?;
+        self.admission.validate(self.resources_count)
+    }
+}
+
+/// Checks schedule with single value for the whole day or value for each hour of the day
+fn validate_hourly(name: &str, values: &[f64]) -> Result<(), CassidyError> {
+    if values.len() != 1 && values.len() != 24 {
+        return Err(CassidyError::InvalidConfig(format!(
+            "{} must contain single value or 24 hourly values",
+            name
+        )));
+    }
+    if values.iter().any(|v| *v < 0.0) {
+        return Err(CassidyError::InvalidConfig(format!(
+            "{} values must not be negative",
+            name
+        )));
+    }
+    Ok(())
+}
+
 impl Config {
-    pub fn validate(self) -> Result<Config, String> {
+    pub fn from_file(file_path: &Path) -> Result<Config, CassidyError> {
+        let data = read_file(file_path)?;
+        toml::from_str::<Config>(&data).map_err(|e| CassidyError::ConfigParse {
+            path: file_path.to_path_buf(),
+            msg: e.to_string(),
+        })
+    }
+
+    pub fn validate(self) -> Result<Config, CassidyError> {
+        if self.process_time_min > self.process_time_max {
+            return Err(CassidyError::InvalidConfig(
+                "process_time_min must not be greater than process_time_max".to_owned(),
+            ));
+        }
+        self.service_time.validate()?;
         if self.lambda < 0.0 {
-            return Err("lambda must be greater than 0".to_owned());
+            return Err(CassidyError::InvalidConfig(
+                "lambda must not be negative".to_owned(),
+            ));
         }
-        if self.lambda_coefs.len() == 0 {
-            return Err("lambda_coefs list must not be empty.".to_owned());
+        if self.lambda_coefs.is_empty() {
+            return Err(CassidyError::InvalidConfig(
+                "lambda_coefs list must not be empty.".to_owned(),
+            ));
         }
         for lp in self.lambda_coefs.iter() {
             if lp.coef < 0.0 {
-                return Err("lambda coefficient must be greater than 0".to_owned());
+                return Err(CassidyError::InvalidConfig(
+                    "lambda coefficient must not be negative".to_owned(),
+                ));
             }
             if lp.time < 0.0 {
-                return Err("lambda timestamp must be greater than 0".to_owned());
+                return Err(CassidyError::InvalidConfig(
+                    "lambda timestamp must be greater than 0".to_owned(),
+                ));
             }
         }
-        if self.sleep_threshold > 100.0 {
-            return Err("sleep_threshold must be from range [0-100]%".to_owned());
+        self.lambda_profile.validate(&self.lambda_coefs)?;
+        for level in self.sleep_levels.iter() {
+            level.validate()?;
         }
-        if self.wakeup_threshold > 100.0 || self.wakeup_threshold < self.sleep_threshold {
-            return Err("wakeup_threshold must be from range [0-100]% and must be greater than sleep_threshold".to_owned());
+        if self.warmup < 0.0 {
+            return Err(CassidyError::InvalidConfig(
+                "warmup must be greater than 0".to_owned(),
+            ));
         }
-        if self.sleep_threshold >= self.wakeup_threshold / 2.0 {
-            println!("Warning] sleep_threshold: {} is greater than wakeup_threshold / 2: {}. This can cause oscillations in stations state", self.sleep_threshold, self.wakeup_threshold)
+        if self.stations_count == 0 {
+            return Err(CassidyError::InvalidConfig(
+                "stations_count must be greater than 0".to_owned(),
+            ));
         }
-        if self.active_power < 0.0 {
-            return Err("active_power must be greater than 0".to_owned());
+        if self.stations.len() > self.stations_count {
+            return Err(CassidyError::InvalidConfig(
+                "stations list must not be longer than stations_count".to_owned(),
+            ));
         }
-        if self.sleep_power < 0.0 {
-            return Err("sleep_power must be greater than 0".to_owned());
+        // stations outside of stations list use only global parameters
+        self.station_params(self.stations.len()).validate()?;
+        for idx in 0..self.stations.len() {
+            if self.stations[idx].sleep_power.is_some() && !self.sleep_levels.is_empty() {
+                return Err(CassidyError::InvalidConfig(format!(
+                    "station {}: sleep_power cannot be overridden when sleep_levels are configured",
+                    idx
+                )));
+            }
+            let params = self.station_params(idx);
+            let valid = params
+                .validate()
+                .and_then(|_| params.lambda_profile.validate(&self.lambda_coefs));
+            valid.map_err(|e| match e {
+                CassidyError::InvalidConfig(msg) => {
+                    CassidyError::InvalidConfig(format!("station {}: {}", idx, msg))
+                }
+                e => e,
+            })?;
         }
-        if self.wakeup_power < 0.0 {
-            return Err("wakeup_power must be greater than 0".to_owned());
+        for (idx, class) in self.service_classes.iter().enumerate() {
+            if self.service_classes[..idx]
+                .iter()
+                .any(|c| c.name == class.name)
+            {
+                return Err(CassidyError::InvalidConfig(format!(
+                    "service class {}: name must be unique",
+                    class.name
+                )));
+            }
+            class.validate(&self).map_err(|e| match e {
+                CassidyError::InvalidConfig(msg) => {
+                    CassidyError::InvalidConfig(format!("service class {}: {}", class.name, msg))
+                }
+                e => e,
+            })?;
+        }
+        self.topology.validate(self.stations_count)?;
+        if let Some(queue) = &self.queue {
+            queue.validate()?;
+        }
+        if let Some(priorities) = &self.priorities {
+            priorities.validate()?;
+        }
+        for (name, schedule) in [
+            ("tariff", &self.tariff),
+            ("carbon_intensity", &self.carbon_intensity),
+        ] {
+            if let Some(values) = schedule {
+                validate_hourly(name, values)?;
+            }
+        }
+        if let Some(spatial) = &self.spatial {
+            spatial.validate()?;
+            if self.topology.coverage().is_none() {
+                return Err(CassidyError::InvalidConfig(
+                    "spatial mode requires Coverage topology with positions of stations".to_owned(),
+                ));
+            }
+            if self.trace.is_some() {
+                return Err(CassidyError::InvalidConfig(
+                    "spatial mode cannot be used with trace".to_owned(),
+                ));
+            }
+        }
+        // recorded arrivals do not carry class or priority of users
+        if self.trace.is_some() && (!self.service_classes.is_empty() || self.priorities.is_some()) {
+            return Err(CassidyError::InvalidConfig(
+                "trace cannot be used with service_classes or priorities".to_owned(),
+            ));
+        }
+        if let SleepControllerKind::MinAwake { count } = self.sleep_controller {
+            if count == 0 || count > self.stations_count {
+                return Err(CassidyError::InvalidConfig(
+                    "sleep_controller count must be from range [1, stations_count]".to_owned(),
+                ));
+            }
         }
         Ok(self)
     }
 
-    pub fn save_deafult(path: PathBuf) -> std::io::Result<()> {
-        let cfg = Config::default();
-        let cfg_str =
-            toml::to_string(&cfg).expect("Internal error: Couldn't parse default config to toml.");
-        let mut file = File::create(&path)?;
-        file.write_all(cfg_str.as_bytes())?;
+    /// Reads histograms of all empirical service time and queue patience distributions
+    pub fn load_service_times(&mut self) -> Result<(), CassidyError> {
+        self.service_time.load()?;
+        for class in self.service_classes.iter_mut() {
+            if let Some(service_time) = class.service_time.as_mut() {
+                service_time.load()?;
+            }
+        }
+        if let Some(patience) = self.queue.as_mut().and_then(|q| q.patience.as_mut()) {
+            patience.load()?;
+        }
+        Ok(())
+    }
+
+    /// Reads daily profiles of global lambda profile and per-station profiles
+    pub fn load_lambda_profiles(&mut self) -> Result<(), CassidyError> {
+        self.lambda_profile.load()?;
+        for station in self.stations.iter_mut() {
+            if let Some(profile) = station.lambda_profile.as_mut() {
+                profile.load()?;
+            }
+        }
         Ok(())
     }
+
+    /// Reads density map of spatial mode
+    pub fn load_density_map(&mut self) -> Result<(), CassidyError> {
+        match self.spatial.as_mut() {
+            Some(spatial) => spatial.placement.load(),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns parameters of station with given index, with overrides from stations list applied
+    pub fn station_params(&self, idx: usize) -> StationParams {
+        let default = StationConfig::default();
+        let station = self.stations.get(idx).unwrap_or(&default);
+        StationParams {
+            resources_count: station.resources_count.unwrap_or(self.resources_count),
+            active_power: station.active_power.unwrap_or(self.active_power),
+            sleep_power: station.sleep_power.unwrap_or(self.sleep_power),
+            wakeup_power: station.wakeup_power.unwrap_or(self.wakeup_power),
+            sleep_threshold: station.sleep_threshold.unwrap_or(self.sleep_threshold),
+            wakeup_threshold: station.wakeup_threshold.unwrap_or(self.wakeup_threshold),
+            lambda_weight: station.lambda_weight.unwrap_or(1.0),
+            phase_offset: station.phase_offset.unwrap_or(0.0),
+            lambda_profile: station
+                .lambda_profile
+                .clone()
+                .unwrap_or_else(|| self.lambda_profile.clone()),
+            power_model: station
+                .power_model
+                .clone()
+                .unwrap_or_else(|| self.power_model.clone()),
+            transition_power: station
+                .transition_power
+                .clone()
+                .unwrap_or_else(|| self.transition_power.clone()),
+            arrival_process: station
+                .arrival_process
+                .clone()
+                .unwrap_or_else(|| self.arrival_process.clone()),
+            admission: station
+                .admission
+                .clone()
+                .unwrap_or_else(|| self.admission.clone()),
+            sleep_levels: if self.sleep_levels.is_empty() {
+                vec![SleepLevel {
+                    name: "sleep".to_owned(),
+                    power: station.sleep_power.unwrap_or(self.sleep_power),
+                    wakeup_delay: self.wakeup_delay,
+                    idle_time: 0,
+                }]
+            } else {
+                self.sleep_levels.clone()
+            },
+        }
+    }
+
+    /// Returns true if any station restricts admission of new arrivals
+    pub fn has_admission_control(&self) -> bool {
+        (0..self.stations_count)
+            .any(|idx| self.station_params(idx).admission != AdmissionControl::Greedy)
+    }
+
+    /// Sets numeric field with given name to given value.
+    /// Nested fields are addressed with dots, e.g. `power_model.p0` or `lambda_coefs.0.coef`.
+    pub fn set_param(&mut self, name: &str, value: f64) -> Result<(), CassidyError> {
+        let mut root = toml::Value::try_from(&*self)
+            .map_err(|e| CassidyError::Internal(format!("cannot serialize config: {}", e)))?;
+        let mut field = &mut root;
+        for key in name.split('.') {
+            let next = match field {
+                toml::Value::Table(table) => table.get_mut(key),
+                toml::Value::Array(array) => {
+                    key.parse::<usize>().ok().and_then(|i| array.get_mut(i))
+                }
+                _ => None,
+            };
+            field = next.ok_or_else(|| {
+                CassidyError::InvalidConfig(format!("Unknown config parameter: {}", name))
+            })?;
+        }
+        match field {
+            toml::Value::Float(f) => *f = value,
+            toml::Value::Integer(i) => {
+                if value.fract() != 0.0 {
+                    return Err(CassidyError::InvalidConfig(format!(
+                        "Parameter {} must be an integer",
+                        name
+                    )));
+                }
+                *i = value as i64;
+            }
+            _ => {
+                return Err(CassidyError::InvalidConfig(format!(
+                    "Parameter {} is not numeric",
+                    name
+                )))
+            }
+        }
+        *self = root.try_into().map_err(|e| {
+            CassidyError::InvalidConfig(format!(
+                "Invalid value {} of parameter {}: {}",
+                value, name, e
+            ))
+        })?;
+        Ok(())
+    }
+
+    pub fn save_deafult(path: PathBuf) -> Result<(), CassidyError> {
+        let cfg = Config::default();
+        let cfg_str = toml::to_string(&cfg).map_err(|e| {
+            CassidyError::Internal(format!("cannot serialize default config: {}", e))
+        })?;
+        File::create(&path)
+            .and_then(|mut file| file.write_all(cfg_str.as_bytes()))
+            .map_err(|source| CassidyError::Io { path, source })
+    }
 }
 
+/// Single variable of legacy walk-over config
 #[derive(Debug, Deserialize, Serialize)]
 pub enum WalkOverType {
     Lambda,
@@ -227,20 +1680,539 @@ pub enum WalkOverType {
     SleepHigh,
 }
 
-impl Display for WalkOverType {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl WalkOverType {
+    /// Name of config field corresponding to the variable
+    pub fn param_name(&self) -> &'static str {
+        match self {
+            WalkOverType::Lambda => "lambda",
+            WalkOverType::SleepLow => "sleep_threshold",
+            WalkOverType::SleepHigh => "wakeup_threshold",
+        }
+    }
+}
+
+/// Way of combining values of several swept parameters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SweepMode {
+    /// Every combination of parameters values
+    #[default]
+    Grid,
+    /// N-th point uses N-th value of every parameter. All parameters must have the same number of values
+    List,
+}
+
+/// Values of single swept parameter, given either explicitly or as range
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SweepValues {
+    List { values: Vec<f64> },
+    Range { start: f64, end: f64, step: f64 },
+}
+
+impl SweepValues {
+    pub fn values(&self) -> Vec<f64> {
         match self {
-            WalkOverType::Lambda => write!(f, "lambda"),
-            WalkOverType::SleepLow => write!(f, "sleep_low"),
-            WalkOverType::SleepHigh => write!(f, "sleep_high"),
+            SweepValues::List { values } => values.clone(),
+            SweepValues::Range { start, end, step } => {
+                let mut values = Vec::new();
+                let mut point = *start;
+                while point <= *end {
+                    values.push(point);
+                    point += step;
+                }
+                values
+            }
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SweepParam {
+    /// Name of numeric config field, see [`Config::set_param`]
+    pub name: String,
+    #[serde(flatten)]
+    pub values: SweepValues,
+}
+
+/// Config of walk-over mode. Either single legacy variable (var, start, end, step)
+/// or list of parameters swept at once can be given.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct WalkOverConfig {
-    pub var: WalkOverType,
+    pub var: Option<WalkOverType>,
     pub start: f64,
     pub end: f64,
     pub step: f64,
+    pub mode: SweepMode,
+    pub params: Vec<SweepParam>,
+}
+
+impl WalkOverConfig {
+    pub fn from_file(file_path: &Path) -> Result<WalkOverConfig, CassidyError> {
+        let data = read_file(file_path)?;
+        let cfg =
+            toml::from_str::<WalkOverConfig>(&data).map_err(|e| CassidyError::ConfigParse {
+                path: file_path.to_path_buf(),
+                msg: e.to_string(),
+            })?;
+        cfg.validate()
+    }
+
+    pub fn validate(mut self) -> Result<WalkOverConfig, CassidyError> {
+        if let Some(var) = self.var.take() {
+            if !self.params.is_empty() {
+                return Err(CassidyError::InvalidConfig(
+                    "walk-over config must contain either var or params list".to_owned(),
+                ));
+            }
+            self.params.push(SweepParam {
+                name: var.param_name().to_owned(),
+                values: SweepValues::Range {
+                    start: self.start,
+                    end: self.end,
+                    step: self.step,
+                },
+            });
+        }
+        if self.params.is_empty() {
+            return Err(CassidyError::InvalidConfig(
+                "walk-over params list must not be empty".to_owned(),
+            ));
+        }
+        for param in self.params.iter() {
+            if let SweepValues::Range { step, .. } = param.values {
+                if step <= 0.0 {
+                    return Err(CassidyError::InvalidConfig(format!(
+                        "{}: step must be greater than 0",
+                        param.name
+                    )));
+                }
+            }
+            if param.values.values().is_empty() {
+                return Err(CassidyError::InvalidConfig(format!(
+                    "{}: there are no values to sweep over",
+                    param.name
+                )));
+            }
+        }
+        if self.mode == SweepMode::List {
+            let len = self.params[0].values.values().len();
+            if self.params.iter().any(|p| p.values.values().len() != len) {
+                return Err(CassidyError::InvalidConfig(
+                    "all walk-over params must have the same number of values in List mode"
+                        .to_owned(),
+                ));
+            }
+        }
+        Ok(self)
+    }
+
+    /// Names of swept parameters
+    pub fn names(&self) -> Vec<String> {
+        self.params.iter().map(|p| p.name.clone()).collect()
+    }
+
+    /// Returns all simulation points. Each point contains values of parameters in order of [`Self::names`].
+    /// In Grid mode the last parameter changes the fastest.
+    pub fn points(&self) -> Vec<Vec<f64>> {
+        let values: Vec<Vec<f64>> = self.params.iter().map(|p| p.values.values()).collect();
+        match self.mode {
+            SweepMode::Grid => {
+                let mut points: Vec<Vec<f64>> = vec![Vec::new()];
+                for param_values in values.iter() {
+                    points = points
+                        .iter()
+                        .flat_map(|point| {
+                            param_values.iter().map(move |v| {
+                                let mut point = point.clone();
+                                point.push(*v);
+                                point
+                            })
+                        })
+                        .collect();
+                }
+                points
+            }
+            SweepMode::List => (0..values[0].len())
+                .map(|i| values.iter().map(|v| v[i]).collect())
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        AdmissionControl, ArrivalProcessKind, Config, LambdaPoint, LambdaProfile, Position,
+        PowerModel, ServiceClass, SleepLevel, SpatialConfig, StationConfig, Topology,
+        TransitionPoint, TransitionPower, WalkOverConfig,
+    };
+    use crate::error::CassidyError;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn set_param() {
+        let mut cfg = Config {
+            power_model: PowerModel::Linear {
+                p0: 100.0,
+                delta_p: 4.0,
+                p_max: 20.0,
+            },
+            ..Default::default()
+        };
+        cfg.set_param("lambda", 20.0).unwrap();
+        cfg.set_param("stations_count", 5.0).unwrap();
+        cfg.set_param("lambda_coefs.1.coef", 2.0).unwrap();
+        cfg.set_param("power_model.p0", 50.0).unwrap();
+        assert_eq!(cfg.lambda, 20.0);
+        assert_eq!(cfg.stations_count, 5);
+        assert_eq!(cfg.lambda_coefs[1].coef, 2.0);
+        assert!(matches!(cfg.power_model, PowerModel::Linear { p0, .. } if p0 == 50.0));
+
+        assert!(cfg.set_param("stations_count", 2.5).is_err());
+        assert!(cfg.set_param("stations_count", -1.0).is_err());
+        assert!(cfg.set_param("redirect_policy", 1.0).is_err());
+        assert!(cfg.set_param("unknown", 1.0).is_err());
+        assert!(cfg.set_param("lambda_coefs.10.coef", 1.0).is_err());
+    }
+
+    #[test]
+    fn walk_over_points() {
+        let legacy: WalkOverConfig =
+            toml::from_str("var = \"SleepLow\"\nstart = 10.0\nend = 20.0\nstep = 5.0").unwrap();
+        let legacy = legacy.validate().unwrap();
+        assert_eq!(legacy.names(), vec!["sleep_threshold"]);
+        assert_eq!(legacy.points(), vec![vec![10.0], vec![15.0], vec![20.0]]);
+
+        let grid = r#"
+            [[params]]
+            name = "lambda"
+            start = 1.0
+            end = 2.0
+            step = 1.0
+            [[params]]
+            name = "stations_count"
+            values = [5, 10]
+        "#;
+        let grid = toml::from_str::<WalkOverConfig>(grid)
+            .unwrap()
+            .validate()
+            .unwrap();
+        assert_eq!(grid.names(), vec!["lambda", "stations_count"]);
+        assert_eq!(
+            grid.points(),
+            vec![
+                vec![1.0, 5.0],
+                vec![1.0, 10.0],
+                vec![2.0, 5.0],
+                vec![2.0, 10.0]
+            ]
+        );
+
+        let list = r#"
+            mode = "List"
+            [[params]]
+            name = "lambda"
+            values = [1.0, 2.0]
+            [[params]]
+            name = "stations_count"
+            values = [5, 10]
+        "#;
+        let list = toml::from_str::<WalkOverConfig>(list)
+            .unwrap()
+            .validate()
+            .unwrap();
+        assert_eq!(list.points(), vec![vec![1.0, 5.0], vec![2.0, 10.0]]);
+
+        let uneven = "mode = \"List\"\nparams = [{ name = \"lambda\", values = [1.0] }, { name = \"stations_count\", values = [5, 10] }]";
+        let uneven = toml::from_str::<WalkOverConfig>(uneven).unwrap();
+        assert!(uneven.validate().is_err());
+    }
+
+    #[test]
+    fn lambda_profile() {
+        let coefs = vec![
+            LambdaPoint {
+                time: 2.0,
+                coef: 1.0,
+            },
+            LambdaPoint {
+                time: 1.0,
+                coef: 3.0,
+            },
+        ];
+        let coef = |profile: &LambdaProfile, time: f64| profile.coef(time, &coefs);
+        assert_eq!(coef(&LambdaProfile::Step, 1.0), 1.0);
+        assert_eq!(coef(&LambdaProfile::Step, 2.5), 3.0);
+        assert_eq!(coef(&LambdaProfile::Step, 3.5), 1.0);
+        assert_eq!(coef(&LambdaProfile::Linear, 1.0), 2.0);
+        assert_eq!(coef(&LambdaProfile::Linear, 2.5), 2.0);
+        assert_eq!(LambdaProfile::Linear.max_coef(&coefs), 3.0);
+
+        let sinusoidal = LambdaProfile::Sinusoidal {
+            mean: 1.0,
+            amplitude: 0.5,
+            period: 24.0,
+            peak: 18.0,
+        };
+        assert!((coef(&sinusoidal, 18.0) - 1.5).abs() < 1e-9);
+        assert!((coef(&sinusoidal, 6.0) - 0.5).abs() < 1e-9);
+        assert!((coef(&sinusoidal, 12.0) - 1.0).abs() < 1e-9);
+        assert_eq!(sinusoidal.max_coef(&coefs), 1.5);
+
+        let mut file = LambdaProfile::File {
+            path: PathBuf::from("tests/references/lambda_profile.csv"),
+            phases: Vec::new(),
+        };
+        file.load().unwrap();
+        assert_eq!(coef(&file, 9.0), 0.75);
+        assert_eq!(coef(&file, 12.0), 1.0);
+        assert_eq!(coef(&file, 18.0), 2.0);
+        // interpolation between the last and the first point goes through midnight
+        assert!((coef(&file, 0.0) - 1.25).abs() < 1e-9);
+        assert!((coef(&file, 24.0 + 3.0) - 0.875).abs() < 1e-9);
+        // converted to microseconds
+        file.scale_time(3600.0 * 1_000_000.0);
+        assert_eq!(coef(&file, 9.0 * 3600.0 * 1_000_000.0), 0.75);
+    }
+
+    #[test]
+    fn topology() {
+        let mut adjacency = Topology::Adjacency {
+            neighbours: vec![vec![1, 1, 0], vec![], vec![1]],
+        };
+        adjacency.prepare();
+        assert_eq!(
+            adjacency,
+            Topology::Adjacency {
+                neighbours: vec![vec![1], vec![0, 2], vec![1]]
+            }
+        );
+        assert!(adjacency.is_neighbour(2, 1));
+        assert!(!adjacency.is_neighbour(0, 2));
+        assert!(!adjacency.is_neighbour(0, 0));
+        assert!(Topology::Full.is_neighbour(0, 0));
+
+        // coverage areas of stations 0 and 1 overlap, station 2 is isolated
+        let position = |x: f64, y: f64| Position { x, y };
+        let mut coverage = Topology::Coverage {
+            positions: vec![
+                position(0.0, 0.0),
+                position(300.0, 400.0),
+                position(0.0, 1500.0),
+            ],
+            radius: 250.0,
+            neighbours: Vec::new(),
+        };
+        coverage.validate(3).unwrap();
+        assert!(coverage.validate(4).is_err());
+        coverage.prepare();
+        assert!(coverage.is_neighbour(0, 1) && coverage.is_neighbour(1, 0));
+        assert!(!coverage.is_neighbour(1, 2) && !coverage.is_neighbour(0, 2));
+    }
+
+    #[test]
+    fn errors() {
+        let err = Config::from_file(Path::new("tests/references/missing.toml")).unwrap_err();
+        assert!(matches!(err, CassidyError::Io { .. }));
+        assert_eq!(err.exit_code(), 74);
+        let err = Config::from_file(Path::new("tests/references/single_sim.log")).unwrap_err();
+        assert!(matches!(err, CassidyError::ConfigParse { .. }));
+        let cfg = Config {
+            stations: vec![Default::default(); 2],
+            stations_count: 2,
+            resources_count: 0,
+            ..Default::default()
+        };
+        let err = cfg.validate().unwrap_err();
+        assert!(matches!(err, CassidyError::InvalidConfig(_)));
+        assert_eq!(err.exit_code(), 78);
+        // walk-over point may set number of stations to 0
+        let mut cfg = Config::default();
+        cfg.set_param("stations_count", 0.0).unwrap();
+        let err = cfg.validate().unwrap_err();
+        assert!(err.to_string().contains("stations_count"), "{}", err);
+        // invalid override of single station is reported with its index
+        let cfg = Config {
+            stations: vec![
+                Default::default(),
+                StationConfig {
+                    arrival_process: Some(ArrivalProcessKind::Pareto { shape: 1.0 }),
+                    ..Default::default()
+                },
+            ],
+            stations_count: 2,
+            ..Default::default()
+        };
+        let err = cfg.validate().unwrap_err();
+        assert!(
+            err.to_string().contains("station 1: arrival_process"),
+            "{}",
+            err
+        );
+        // sleep levels are shared by all stations
+        let cfg = Config {
+            stations: vec![StationConfig {
+                sleep_power: Some(5.0),
+                ..Default::default()
+            }],
+            sleep_levels: vec![SleepLevel {
+                name: "light".to_owned(),
+                power: 20.0,
+                wakeup_delay: 1,
+                idle_time: 0,
+            }],
+            ..Default::default()
+        };
+        let err = cfg.validate().unwrap_err();
+        assert!(
+            err.to_string().contains("station 0: sleep_power"),
+            "{}",
+            err
+        );
+        // names of service classes are used in CSV header
+        let class = |name: &str| ServiceClass {
+            name: name.to_owned(),
+            share: 1.0,
+            process_time_min: None,
+            process_time_max: None,
+            service_time: None,
+            blocks: Default::default(),
+            priority: 0,
+        };
+        for names in [
+            vec![""],
+            vec!["voice,video"],
+            vec!["\"voice\""],
+            vec!["a", "b", "a"],
+        ] {
+            let cfg = Config {
+                service_classes: names.into_iter().map(class).collect(),
+                ..Default::default()
+            };
+            let err = cfg.validate().unwrap_err();
+            assert!(err.to_string().contains("name must"), "{}", err);
+        }
+        // replayed users have no class
+        let cfg = Config {
+            service_classes: vec![class("voice")],
+            trace: Some(PathBuf::from("tests/references/trace.csv")),
+            ..Default::default()
+        };
+        let err = cfg.validate().unwrap_err();
+        assert!(err.to_string().contains("trace cannot"), "{}", err);
+        // spatial mode needs positions of stations
+        let cfg = Config {
+            spatial: Some(SpatialConfig::default()),
+            ..Default::default()
+        };
+        let err = cfg.validate().unwrap_err();
+        assert!(err.to_string().contains("Coverage topology"), "{}", err);
+    }
+
+    #[test]
+    fn transition_power() {
+        let powers = (10.0, 110.0);
+        assert_eq!(
+            TransitionPower::Constant.mean_power(0.2, 0.4, powers, 500.0),
+            500.0
+        );
+        let ramp = TransitionPower::Ramp;
+        assert_eq!(ramp.mean_power(0.0, 1.0, powers, 500.0), 60.0);
+        assert!((ramp.mean_power(0.5, 1.0, powers, 500.0) - 85.0).abs() < 1e-9);
+        // peak in the middle of transition
+        let peak = TransitionPower::Piecewise {
+            points: vec![
+                TransitionPoint {
+                    progress: 0.0,
+                    power: 0.0,
+                },
+                TransitionPoint {
+                    progress: 50.0,
+                    power: 100.0,
+                },
+                TransitionPoint {
+                    progress: 100.0,
+                    power: 0.0,
+                },
+            ],
+        };
+        peak.validate().unwrap();
+        assert!((peak.mean_power(0.0, 1.0, powers, 500.0) - 50.0).abs() < 1e-9);
+        assert!((peak.mean_power(0.25, 0.75, powers, 500.0) - 75.0).abs() < 1e-9);
+        assert_eq!(peak.mean_power(0.5, 0.5, powers, 500.0), 100.0);
+        let unsorted = TransitionPower::Piecewise {
+            points: vec![
+                TransitionPoint {
+                    progress: 50.0,
+                    power: 1.0,
+                },
+                TransitionPoint {
+                    progress: 10.0,
+                    power: 1.0,
+                },
+            ],
+        };
+        assert!(unsorted.validate().is_err());
+    }
+
+    #[test]
+    fn hourly_schedules() {
+        let cfg = Config {
+            tariff: Some(vec![0.2]),
+            carbon_intensity: Some(vec![300.0; 24]),
+            ..Default::default()
+        };
+        assert!(cfg.validate().is_ok());
+        let cfg = Config {
+            tariff: Some(vec![0.2; 12]),
+            ..Default::default()
+        };
+        assert!(cfg.validate().is_err());
+        let cfg = Config {
+            carbon_intensity: Some(vec![-1.0]),
+            ..Default::default()
+        };
+        assert!(cfg.validate().is_err());
+    }
+
+    #[test]
+    fn admission() {
+        let trunk = AdmissionControl::TrunkReservation {
+            thresholds: vec![4, 2],
+        };
+        assert_eq!(trunk.reserved(0), 4);
+        assert_eq!(trunk.reserved(1), 2);
+        assert_eq!(trunk.reserved(2), 0);
+        assert!(trunk.validate(5).is_ok());
+        assert!(trunk.validate(4).is_err());
+
+        let cfg = Config {
+            stations_count: 3,
+            resources_count: 10,
+            stations: vec![
+                Default::default(),
+                StationConfig {
+                    admission: Some(AdmissionControl::GuardChannel { reserved: 3 }),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        assert!(cfg.has_admission_control());
+        assert_eq!(cfg.station_params(0).admission, AdmissionControl::Greedy);
+        assert_eq!(cfg.station_params(1).admission.reserved(0), 3);
+        assert!(!Config::default().has_admission_control());
+        // reservation is validated against resources of every station
+        let cfg = Config {
+            stations: vec![StationConfig {
+                resources_count: Some(3),
+                admission: Some(AdmissionControl::GuardChannel { reserved: 3 }),
+                ..Default::default()
+            }],
+            ..cfg
+        };
+        let err = cfg.validate().unwrap_err();
+        assert!(err.to_string().contains("station 0: admission"), "{}", err);
+    }
 }
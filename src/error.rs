@@ -0,0 +1,58 @@
+use std::{fmt::Display, path::PathBuf};
+
+/// Error of simulation setup or run.
+/// Each kind of error has its own process exit code, so user mistakes
+/// can be told apart from simulator bugs.
+#[derive(Debug)]
+pub enum CassidyError {
+    /// Config file is not valid TOML or does not match config structure
+    ConfigParse { path: PathBuf, msg: String },
+    /// Config values are out of allowed range or inconsistent
+    InvalidConfig(String),
+    /// Invalid command line or run options
+    Usage(String),
+    /// File could not be read or written
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// Internal invariant of simulator was violated
+    Internal(String),
+}
+
+impl CassidyError {
+    /// Process exit code, following sysexits.h convention
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            CassidyError::Usage(_) => 64,
+            CassidyError::Internal(_) => 70,
+            CassidyError::Io { .. } => 74,
+            CassidyError::ConfigParse { .. } | CassidyError::InvalidConfig(_) => 78,
+        }
+    }
+}
+
+impl Display for CassidyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CassidyError::ConfigParse { path, msg } => {
+                write!(f, "Cannot parse config file {}: {}", path.display(), msg)
+            }
+            CassidyError::InvalidConfig(msg) => write!(f, "Invalid config: {}", msg),
+            CassidyError::Usage(msg) => write!(f, "Invalid options: {}", msg),
+            CassidyError::Io { path, source } => {
+                write!(f, "Cannot access file {}: {}", path.display(), source)
+            }
+            CassidyError::Internal(msg) => write!(f, "Internal error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for CassidyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CassidyError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,35 @@
+//! Simulation of radiocommunication basestations system with sleep state logic.
+//!
+//! Simulation is configured with [`Config`] and run options passed to [`SimulationBuilder`],
+//! which creates [`SimContainer`] ready to be run.
+
+pub mod arrival;
+pub mod basestation;
+pub mod config;
+pub mod error;
+pub mod logger;
+pub mod mobility;
+pub mod queue;
+pub mod redirect;
+pub mod sim_container;
+pub mod sleep;
+pub mod spatial;
+pub mod stats;
+pub mod trace;
+pub mod user;
+
+pub use basestation::BaseStationResult;
+pub use config::{
+    AdmissionControl, ArrivalProcessKind, BlocksDistribution, BlocksWeight, Config, HistogramBin,
+    Hotspot, LambdaPoint, LambdaProfile, MmppState, MobilityModel, Position, PowerModel,
+    PowerPoint, PriorityConfig, QueueConfig, QueueDiscipline, QueueScope, RedirectPolicyKind,
+    ServiceClass, ServiceTime, SleepControllerKind, SleepLevel, SpatialConfig, StationConfig,
+    SweepMode, SweepParam, SweepValues, Topology, TransitionPoint, TransitionPower, UserPlacement,
+    WalkOverConfig, WalkOverType,
+};
+pub use error::CassidyError;
+pub use sim_container::{
+    AccountResult, ClassResult, PriorityResult, QueueResult, ResultsSpread, SchedulerKind,
+    SimContainer, SimOptions, SimResults, SimulationBuilder,
+};
+pub use stats::MetricStats;
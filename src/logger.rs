@@ -1,4 +1,4 @@
-use crate::config::Config;
+use crate::{config::Config, error::CassidyError};
 use std::{
     fs::{remove_file, File},
     io::Write,
@@ -12,14 +12,18 @@ pub struct Logger {
 }
 
 impl Logger {
-    pub fn new(enable: bool, cfg: &Config, path: &PathBuf) -> Result<Logger, String> {
+    pub fn new(enable: bool, cfg: &Config, path: &PathBuf) -> Result<Logger, CassidyError> {
         if enable {
             if path.exists() {
                 let _ = remove_file(path);
             }
+            let file = File::create(path).map_err(|source| CassidyError::Io {
+                path: path.clone(),
+                source,
+            })?;
             Ok(Logger {
                 buffer: Vec::with_capacity(cfg.log_buffer),
-                file: Some(File::create(&path).unwrap()),
+                file: Some(file),
             })
         } else {
             Ok(Logger {
@@ -60,8 +64,10 @@ mod test {
 
     #[test]
     fn logger() {
-        let mut cfg = Config::default();
-        cfg.log_buffer = 5;
+        let cfg = Config {
+            log_buffer: 5,
+            ..Default::default()
+        };
         let mut logger = Logger::new(true, &cfg, &PathBuf::from("test_logger.log")).unwrap();
         for i in 0..5 {
             logger.log(format!("Line: {}", i), 0, &cfg);
@@ -1,79 +1,106 @@
-use crate::{
-    config::{Cli, Config},
-    sim_container::SimContainer,
-};
+use crate::cli::Cli;
+use cassidy::{CassidyError, Config, SimContainer, SimResults};
 use clap::Parser;
 use rayon::prelude::*;
-use sim_container::SimResults;
-use std::{io::Write, iter::zip};
+use std::{collections::HashMap, iter::zip, process::ExitCode};
 
-mod basestation;
-mod config;
-mod logger;
-mod sim_container;
-mod user;
+mod cli;
 
-fn main() {
-    let cli = match Cli::parse().validate() {
-        Ok(c) => c,
-        Err(e) => panic!("{}", e),
-    };
+/// Returns CSV values of given results in order of given header. Missing columns are left empty
+fn csv_row(header: &str, res: &SimResults) -> String {
+    let res_header = res.get_csv_header();
+    let res_csv = res.get_csv();
+    let values: HashMap<&str, &str> = zip(res_header.split(','), res_csv.split(',')).collect();
+    header
+        .split(',')
+        .map(|name| values.get(name).copied().unwrap_or(""))
+        .collect::<Vec<&str>>()
+        .join(",")
+}
+
+fn main() -> ExitCode {
+    match run_cli() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+fn run_cli() -> Result<(), CassidyError> {
+    let cli = Cli::parse().validate()?;
     if let Some(path) = &cli.save_default_config {
-        match Config::save_deafult(path.clone()) {
-            Err(e) => panic!("{}", e),
-            Ok(_) => (),
-        };
+        Config::save_deafult(path.clone())?;
     }
-    let walk_cfg = match cli.create_walk_over_config() {
-        Ok(c) => c,
-        Err(e) => panic!("{}", e),
-    };
+    let cfg = cli.create_config()?;
+    let walk_cfg = cli.create_walk_over_config()?;
 
     let report = match walk_cfg {
         Some(walk_cfg) => {
-            let mut points: Vec<f64> = Vec::new();
-            let mut point = walk_cfg.start;
-            while point <= walk_cfg.end {
-                points.push(point);
-                point += walk_cfg.step;
-            }
-            let results: Vec<SimResults> = points
+            let names = walk_cfg.names();
+            let points = walk_cfg.points();
+            // prepare all scenes upfront, so invalid parameters are reported before any simulation
+            let scenes: Vec<SimContainer> = points
+                .iter()
+                .map(|point| {
+                    let mut cfg = cfg.clone();
+                    for (name, value) in zip(names.iter(), point.iter()) {
+                        cfg.set_param(name, *value)?;
+                    }
+                    cli.create_builder(cfg).build()
+                })
+                .collect::<Result<_, CassidyError>>()?;
+            let results: Vec<SimResults> = scenes
                 .par_iter()
                 .enumerate()
-                .map(|(idx, p)| {
-                    let mut scene = match SimContainer::new() {
-                        Ok(s) => s,
-                        Err(e) => panic!("{}", e),
-                    };
-                    scene.update_param(&walk_cfg.var, *p);
-                    return scene.run(idx);
-                })
-                .collect();
+                .map(|(idx, scene)| scene.run(idx))
+                .collect::<Result<_, CassidyError>>()?;
+            for (point, res) in zip(points.iter(), results.iter()) {
+                if let Some(warmup) = res.detected_warmup {
+                    let params: Vec<String> = zip(names.iter(), point.iter())
+                        .map(|(name, value)| format!("{}={}", name, value))
+                        .collect();
+                    println!(
+                        "Detected warm-up period for {}: {:.4} h",
+                        params.join(", "),
+                        warmup
+                    );
+                }
+            }
 
-            let mut report = format!("{},{}\n", walk_cfg.var, results[0].get_csv_header());
-            for (param, res) in zip(points.iter(), results.iter()) {
-                report += &format!("{},", param);
-                report += &res.get_csv();
+            // number of stations may be swept too, so columns are taken from the widest result
+            let header = results
+                .iter()
+                .max_by_key(|r| r.stations.len())
+                .unwrap()
+                .get_csv_header();
+            let mut report = format!("{},{}\n", names.join(","), header);
+            for (point, res) in zip(points.iter(), results.iter()) {
+                for value in point.iter() {
+                    report += &format!("{},", value);
+                }
+                report += &csv_row(&header, res);
                 report += "\n";
             }
             report
         }
         None => {
-            let scene = match SimContainer::new() {
-                Ok(s) => s,
-                Err(e) => panic!("{}", e),
-            };
-            let res = scene.run(0);
+            let scene = cli.create_builder(cfg).build()?;
+            let res = scene.run(0)?;
+            if let Some(warmup) = res.detected_warmup {
+                println!("Detected warm-up period: {:.4} h", warmup);
+            }
             let report = res.get_report();
             println!("\n=================== Average simulation results ===================");
             println!("{}", report);
             report
         }
     };
-    let file = std::fs::File::create("sim_report");
-    match file {
-        Ok(mut f) => f.write_all(report.as_bytes()).unwrap(),
-        Err(e) => println!("{}", e),
-    };
+    std::fs::write("sim_report", report).map_err(|source| CassidyError::Io {
+        path: "sim_report".into(),
+        source,
+    })?;
     println!("Simulation finished. Results saved in sim_report file.");
+    Ok(())
 }
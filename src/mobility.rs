@@ -0,0 +1,185 @@
+//! Movement of users in spatial mode.
+//!
+//! Positions of users are updated lazily, when simulation checks handovers, so each user keeps
+//! timestamp of its last update and catches up with all legs and pauses since that time.
+
+use std::f64::consts::PI;
+
+use rand::{rngs::StdRng, Rng};
+
+use crate::config::{MobilityModel, Position, SpatialConfig};
+
+/// Position and current leg of moving user
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Movement {
+    pub position: Position,
+    destination: Position,
+    speed: f64,       // [m/s]
+    pause_until: u64, // [us]
+    last_update: u64, // [us]
+}
+
+impl Movement {
+    /// Starts movement of user, which appeared at given position and time
+    pub fn new(
+        position: Position,
+        time: u64,
+        spatial: &SpatialConfig,
+        rng: &mut StdRng,
+    ) -> Movement {
+        let mut movement = Movement {
+            position,
+            destination: position,
+            speed: 0.0,
+            pause_until: time,
+            last_update: time,
+        };
+        movement.next_leg(spatial, rng);
+        movement
+    }
+
+    /// Draws destination and speed of the next leg, starting at current position
+    fn next_leg(&mut self, spatial: &SpatialConfig, rng: &mut StdRng) {
+        let (speed_min, speed_max) = match spatial.mobility {
+            MobilityModel::Static => return,
+            MobilityModel::RandomWaypoint {
+                speed_min,
+                speed_max,
+                ..
+            } => {
+                self.destination = Position {
+                    x: rng.gen::<f64>() * spatial.width,
+                    y: rng.gen::<f64>() * spatial.height,
+                };
+                (speed_min, speed_max)
+            }
+            MobilityModel::RandomDirection {
+                speed_min,
+                speed_max,
+                ..
+            } => {
+                // distance to the border of area in given direction
+                let border = |from: f64, max: f64, direction: f64| {
+                    if direction > 0.0 {
+                        (max - from) / direction
+                    } else if direction < 0.0 {
+                        -from / direction
+                    } else {
+                        f64::INFINITY
+                    }
+                };
+                // user standing at the border must not choose direction leading outside of area
+                let (sin, cos, distance) = loop {
+                    let (sin, cos) = (rng.gen::<f64>() * 2.0 * PI).sin_cos();
+                    let distance = border(self.position.x, spatial.width, cos).min(border(
+                        self.position.y,
+                        spatial.height,
+                        sin,
+                    ));
+                    if distance > 1e-6 {
+                        break (sin, cos, distance);
+                    }
+                };
+                self.destination = Position {
+                    x: (self.position.x + distance * cos).clamp(0.0, spatial.width),
+                    y: (self.position.y + distance * sin).clamp(0.0, spatial.height),
+                };
+                (speed_min, speed_max)
+            }
+        };
+        self.speed = rng.gen_range(speed_min..=speed_max);
+    }
+
+    /// Moves user along its legs up to given time
+    pub fn advance(&mut self, time: u64, spatial: &SpatialConfig, rng: &mut StdRng) {
+        let pause = match spatial.mobility {
+            MobilityModel::Static => return,
+            MobilityModel::RandomWaypoint { pause, .. }
+            | MobilityModel::RandomDirection { pause, .. } => pause * 1000,
+        };
+        while self.last_update < time {
+            if self.pause_until > self.last_update {
+                self.last_update = self.pause_until.min(time);
+                continue;
+            }
+            let dx = self.destination.x - self.position.x;
+            let dy = self.destination.y - self.position.y;
+            let distance = dx.hypot(dy);
+            let travel = self.speed * (time - self.last_update) as f64 / 1_000_000.0;
+            if travel < distance {
+                self.position.x += dx * travel / distance;
+                self.position.y += dy * travel / distance;
+                self.last_update = time;
+                return;
+            }
+            // end of leg is reached before given time
+            self.position = self.destination;
+            self.last_update += (distance / self.speed * 1_000_000.0) as u64;
+            self.pause_until = self.last_update + pause;
+            self.next_leg(spatial, rng);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Movement;
+    use crate::config::{MobilityModel, Position, SpatialConfig};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn movement() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let start = Position { x: 50.0, y: 50.0 };
+        for mobility in [
+            MobilityModel::RandomWaypoint {
+                speed_min: 10.0,
+                speed_max: 10.0,
+                pause: 0,
+            },
+            MobilityModel::RandomDirection {
+                speed_min: 10.0,
+                speed_max: 10.0,
+                pause: 0,
+            },
+        ] {
+            let spatial = SpatialConfig {
+                width: 100.0,
+                height: 100.0,
+                mobility,
+                ..Default::default()
+            };
+            let mut movement = Movement::new(start, 0, &spatial, &mut rng);
+            // within the first leg user moves along straight line with constant speed
+            movement.advance(1_000_000, &spatial, &mut rng);
+            let p = movement.position;
+            let distance = (p.x - start.x).hypot(p.y - start.y);
+            assert!((distance - 10.0).abs() < 1e-6, "distance: {}", distance);
+            // user never leaves area
+            for t in 2..1000 {
+                movement.advance(t * 1_000_000, &spatial, &mut rng);
+                let p = movement.position;
+                assert!((0.0..=100.0).contains(&p.x) && (0.0..=100.0).contains(&p.y));
+            }
+        }
+
+        // random direction leg ends at border, where user waits
+        let spatial = SpatialConfig {
+            width: 100.0,
+            height: 100.0,
+            mobility: MobilityModel::RandomDirection {
+                speed_min: 1000.0,
+                speed_max: 1000.0,
+                pause: 60_000,
+            },
+            ..Default::default()
+        };
+        let mut movement = Movement::new(start, 0, &spatial, &mut rng);
+        movement.advance(1_000_000, &spatial, &mut rng);
+        let p = movement.position;
+        let border_distance = p.x.min(p.y).min(100.0 - p.x).min(100.0 - p.y);
+        assert!(border_distance < 1e-6, "position: {:?}", p);
+        movement.advance(50_000_000, &spatial, &mut rng);
+        assert_eq!(movement.position, p);
+    }
+}
@@ -0,0 +1,244 @@
+//! Waiting queues of users, which could not be served by any station.
+
+use std::collections::{BTreeSet, VecDeque};
+
+use crate::{
+    basestation::BaseStation,
+    config::{QueueConfig, QueueDiscipline, QueueScope},
+    user::User,
+};
+
+/// User waiting for free resources
+#[derive(Debug)]
+pub struct QueuedUser {
+    pub user: User,
+    pub arrival: u64,  // [us]
+    pub deadline: u64, // [us], time of abandoning the queue
+}
+
+/// Waiting queues of the whole network with their statistics
+#[derive(Debug)]
+pub struct Queues {
+    queues: Vec<VecDeque<QueuedUser>>,
+    // (deadline, user id, queue index) of all waiting users with finite patience
+    deadlines: BTreeSet<(u64, usize, usize)>,
+    scope: QueueScope,
+    discipline: QueueDiscipline,
+    max_length: usize,
+    pub queued_users: usize,
+    pub admitted_users: usize,
+    pub abandoned_users: usize,
+    pub total_wait: u64,      // [us], of admitted users
+    pub length_integral: f64, // [users * us]
+    last_update: u64,
+}
+
+impl Queues {
+    pub fn new(cfg: &QueueConfig, stations_count: usize) -> Queues {
+        let count = match cfg.scope {
+            QueueScope::Global => 1,
+            QueueScope::Station => stations_count,
+        };
+        Queues {
+            queues: (0..count).map(|_| VecDeque::new()).collect(),
+            deadlines: BTreeSet::new(),
+            scope: cfg.scope,
+            discipline: cfg.discipline,
+            max_length: cfg.max_length,
+            queued_users: 0,
+            admitted_users: 0,
+            abandoned_users: 0,
+            total_wait: 0,
+            length_integral: 0.0,
+            last_update: 0,
+        }
+    }
+
+    /// Index of queue used by given station
+    fn queue_idx(&self, station: usize) -> usize {
+        match self.scope {
+            QueueScope::Global => 0,
+            QueueScope::Station => station,
+        }
+    }
+
+    /// Returns total number of waiting users
+    pub fn waiting_users(&self) -> usize {
+        self.queues.iter().map(|q| q.len()).sum()
+    }
+
+    /// Accumulates queue length from last update up to given timestamp.
+    /// Must be called before each change of queues
+    pub fn update_counters(&mut self, time: u64) {
+        self.length_integral += (time - self.last_update) as f64 * self.waiting_users() as f64;
+        self.last_update = time;
+    }
+
+    /// Discards statistics accumulated so far, e.g. at the end of warm-up period
+    pub fn reset_counters(&mut self) {
+        self.queued_users = 0;
+        self.admitted_users = 0;
+        self.abandoned_users = 0;
+        self.total_wait = 0;
+        self.length_integral = 0.0;
+    }
+
+    /// Puts user, who arrived at given station, into waiting queue.
+    /// If the queue is full, user is returned back
+    pub fn push(
+        &mut self,
+        station: usize,
+        user: User,
+        time: u64,
+        patience: u64,
+    ) -> Result<(), User> {
+        let idx = self.queue_idx(station);
+        if self.queues[idx].len() >= self.max_length {
+            return Err(user);
+        }
+        self.update_counters(time);
+        let deadline = time.saturating_add(patience);
+        if deadline != u64::MAX {
+            self.deadlines.insert((deadline, user.id, idx));
+        }
+        let queue = &mut self.queues[idx];
+        let position = match self.discipline {
+            QueueDiscipline::Fifo => queue.len(),
+            // behind all users with the same or higher priority
+            QueueDiscipline::Priority => queue
+                .iter()
+                .position(|q| q.user.priority < user.priority)
+                .unwrap_or(queue.len()),
+        };
+        queue.insert(
+            position,
+            QueuedUser {
+                user,
+                arrival: time,
+                deadline,
+            },
+        );
+        self.queued_users += 1;
+        Ok(())
+    }
+
+    /// Returns timestamp of the earliest abandonment, u64::MAX if no user can abandon
+    pub fn next_deadline(&self) -> u64 {
+        self.deadlines
+            .first()
+            .map_or(u64::MAX, |(deadline, _, _)| *deadline)
+    }
+
+    /// Removes and returns all users, whose patience expired up to given time
+    pub fn abandon(&mut self, time: u64) -> Vec<QueuedUser> {
+        self.update_counters(time);
+        let mut abandoned = Vec::new();
+        while let Some(&(deadline, id, idx)) = self.deadlines.first() {
+            if deadline > time {
+                break;
+            }
+            self.deadlines.pop_first();
+            if let Some(position) = self.queues[idx].iter().position(|q| q.user.id == id) {
+                abandoned.extend(self.queues[idx].remove(position));
+            }
+        }
+        self.abandoned_users += abandoned.len();
+        abandoned
+    }
+
+    /// Moves waiting users from the head of queue of given station into it, while they fit.
+    /// Holding time of admitted users starts at admission. Returns ids of admitted users
+    pub fn admit(&mut self, station: &mut BaseStation, time: u64) -> Vec<usize> {
+        let idx = self.queue_idx(station.id);
+        let mut admitted = Vec::new();
+        if !station.is_active() {
+            return admitted;
+        }
+        self.update_counters(time);
+        while let Some(head) = self.queues[idx].front() {
+            if !station.can_serve(head.user.blocks) {
+                break;
+            }
+            let QueuedUser {
+                mut user,
+                arrival,
+                deadline,
+            } = self.queues[idx].pop_front().unwrap();
+            self.deadlines.remove(&(deadline, user.id, idx));
+            self.total_wait += time - arrival;
+            user.end = time + (user.end - user.start);
+            user.start = time;
+            admitted.push(user.id);
+            let _ = station.redirect_here(user);
+        }
+        self.admitted_users += admitted.len();
+        admitted
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Queues;
+    use crate::{
+        basestation::BaseStation,
+        config::{Config, QueueConfig, QueueDiscipline, QueueScope},
+        user::User,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn user(id: usize, blocks: usize, priority: usize) -> User {
+        User::test(id, 100)
+            .with_blocks(blocks)
+            .with_priority(priority)
+    }
+
+    #[test]
+    fn queues() {
+        let cfg = Config {
+            stations_count: 2,
+            resources_count: 2,
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut stations: Vec<BaseStation> = (0..2)
+            .map(|i| BaseStation::new(i, &cfg, 1.0, &mut rng))
+            .collect();
+        let queue_cfg = QueueConfig {
+            scope: QueueScope::Station,
+            max_length: 3,
+            discipline: QueueDiscipline::Priority,
+            patience: None,
+        };
+        let mut queues = Queues::new(&queue_cfg, 2);
+        queues.push(0, user(0, 1, 0), 10, 50).unwrap();
+        queues.push(0, user(1, 1, 2), 20, 50).unwrap();
+        queues.push(0, user(2, 2, 1), 20, u64::MAX).unwrap();
+        assert!(queues.push(0, user(3, 1, 0), 20, 50).is_err());
+        queues.push(1, user(4, 1, 0), 20, 50).unwrap();
+        assert_eq!(queues.next_deadline(), 60);
+
+        // higher priority first, user 2 does not fit after user 1
+        assert_eq!(queues.admit(&mut stations[0], 30), vec![1]);
+        assert_eq!(stations[0].get_usage_raw(), 1);
+        assert_eq!(queues.total_wait, 10);
+        assert_eq!(queues.next_deadline(), 60);
+
+        // user 0 and user 4 abandon, user 2 has infinite patience
+        let abandoned: Vec<usize> = queues.abandon(70).iter().map(|q| q.user.id).collect();
+        assert_eq!(abandoned, vec![0, 4]);
+        assert_eq!(queues.waiting_users(), 1);
+        assert_eq!(queues.next_deadline(), u64::MAX);
+        stations[0].release_all();
+        assert_eq!(queues.admit(&mut stations[0], 80), vec![2]);
+        assert_eq!(
+            (
+                queues.queued_users,
+                queues.admitted_users,
+                queues.abandoned_users
+            ),
+            (4, 2, 2)
+        );
+        // queue length integral: 1 user 10-20, 4 users 20-30, 3 users 30-70, 1 user 70-80
+        assert_eq!(queues.length_integral, 10.0 + 40.0 + 120.0 + 10.0);
+    }
+}
@@ -0,0 +1,388 @@
+use rand::{rngs::StdRng, Rng};
+
+use crate::{
+    basestation::BaseStation, config::Config, config::RedirectPolicyKind, spatial::covers_user,
+    user::User,
+};
+
+/// Strategy of choosing station for user that could not be served by its own station.
+/// Only neighbours of that station in configured topology are considered, never the station itself,
+/// which may refuse user because of admission control although it has free resources.
+/// In spatial mode, only stations covering position of the user are considered.
+pub trait RedirectPolicy {
+    /// Returns index of station that given user should be redirected to,
+    /// or None if user should be dropped.
+    /// `from` is index of station that could not serve the user.
+    fn select(
+        &mut self,
+        from: usize,
+        user: &User,
+        stations: &[BaseStation],
+        cfg: &Config,
+        rng: &mut StdRng,
+    ) -> Option<usize>;
+}
+
+pub fn create_policy(kind: RedirectPolicyKind) -> Box<dyn RedirectPolicy> {
+    match kind {
+        RedirectPolicyKind::LeastLoaded => Box::new(LeastLoaded),
+        RedirectPolicyKind::RoundRobin => Box::new(RoundRobin { last: 0 }),
+        RedirectPolicyKind::Random => Box::new(RandomFree),
+        RedirectPolicyKind::FirstFit => Box::new(FirstFit),
+        RedirectPolicyKind::Neighbour => Box::new(Neighbour),
+        RedirectPolicyKind::NoRedirect => Box::new(NoRedirect),
+    }
+}
+
+/// Returns true if given station can take user, who could not be served by station `from`:
+/// it is active neighbour of that station with enough free resource blocks,
+/// which covers position of the user in spatial mode
+fn can_take(from: usize, station: &BaseStation, user: &User, cfg: &Config) -> bool {
+    station.id != from
+        && cfg.topology.is_neighbour(from, station.id)
+        && station.is_active()
+        && station.can_serve(user.blocks)
+        && covers_user(user, station.id, cfg)
+}
+
+/// Returns index of station with lowest usage from given candidates
+fn least_loaded<'a>(candidates: impl Iterator<Item = &'a BaseStation>) -> Option<usize> {
+    candidates
+        .min_by(|x, y| x.get_usage().partial_cmp(&y.get_usage()).unwrap())
+        .map(|s| s.id)
+}
+
+pub struct LeastLoaded;
+
+impl RedirectPolicy for LeastLoaded {
+    fn select(
+        &mut self,
+        from: usize,
+        user: &User,
+        stations: &[BaseStation],
+        cfg: &Config,
+        _rng: &mut StdRng,
+    ) -> Option<usize> {
+        least_loaded(stations.iter().filter(|s| can_take(from, s, user, cfg)))
+    }
+}
+
+pub struct RoundRobin {
+    last: usize,
+}
+
+impl RedirectPolicy for RoundRobin {
+    fn select(
+        &mut self,
+        from: usize,
+        user: &User,
+        stations: &[BaseStation],
+        cfg: &Config,
+        _rng: &mut StdRng,
+    ) -> Option<usize> {
+        let n = stations.len();
+        let idx = (1..=n)
+            .map(|offset| (self.last + offset) % n)
+            .find(|idx| can_take(from, &stations[*idx], user, cfg))?;
+        self.last = idx;
+        Some(idx)
+    }
+}
+
+pub struct RandomFree;
+
+impl RedirectPolicy for RandomFree {
+    fn select(
+        &mut self,
+        from: usize,
+        user: &User,
+        stations: &[BaseStation],
+        cfg: &Config,
+        rng: &mut StdRng,
+    ) -> Option<usize> {
+        let candidates: Vec<usize> = stations
+            .iter()
+            .filter(|s| can_take(from, s, user, cfg))
+            .map(|s| s.id)
+            .collect();
+        if candidates.is_empty() {
+            return None;
+        }
+        Some(candidates[rng.gen_range(0..candidates.len())])
+    }
+}
+
+pub struct FirstFit;
+
+impl RedirectPolicy for FirstFit {
+    fn select(
+        &mut self,
+        from: usize,
+        user: &User,
+        stations: &[BaseStation],
+        cfg: &Config,
+        _rng: &mut StdRng,
+    ) -> Option<usize> {
+        stations
+            .iter()
+            .find(|s| can_take(from, s, user, cfg))
+            .map(|s| s.id)
+    }
+}
+
+pub struct Neighbour;
+
+impl RedirectPolicy for Neighbour {
+    fn select(
+        &mut self,
+        from: usize,
+        user: &User,
+        stations: &[BaseStation],
+        cfg: &Config,
+        _rng: &mut StdRng,
+    ) -> Option<usize> {
+        let n = stations.len();
+        let prev = (from + n - 1) % n;
+        let next = (from + 1) % n;
+        let candidates = [prev, next]
+            .into_iter()
+            .map(|idx| &stations[idx])
+            .filter(|s| can_take(from, s, user, cfg));
+        least_loaded(candidates)
+    }
+}
+
+pub struct NoRedirect;
+
+impl RedirectPolicy for NoRedirect {
+    fn select(
+        &mut self,
+        _from: usize,
+        _user: &User,
+        _stations: &[BaseStation],
+        _cfg: &Config,
+        _rng: &mut StdRng,
+    ) -> Option<usize> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::create_policy;
+    use crate::{
+        basestation::{BaseStation, BaseStationState},
+        config::{Config, Position, RedirectPolicyKind, StationConfig, Topology},
+        user::User,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// User requesting given number of blocks
+    fn request(blocks: usize) -> User {
+        User::test(0, 10).with_blocks(blocks)
+    }
+
+    fn create_stations(cfg: &Config, usage: &[usize], rng: &mut StdRng) -> Vec<BaseStation> {
+        let mut stations = Vec::new();
+        for (i, count) in usage.iter().enumerate() {
+            let mut station = BaseStation::new(i, cfg, 1.0, rng);
+            for id in 0..*count {
+                station.force_add_user(User::test(id, 10));
+            }
+            stations.push(station);
+        }
+        stations
+    }
+
+    #[test]
+    fn policies() {
+        let cfg = Config {
+            resources_count: 2,
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut stations = create_stations(&cfg, &[2, 1, 2, 0, 2], &mut rng);
+        stations[3].state = BaseStationState::Sleep(0);
+
+        let mut policy = create_policy(RedirectPolicyKind::LeastLoaded);
+        assert_eq!(
+            policy.select(0, &request(1), &stations, &cfg, &mut rng),
+            Some(1)
+        );
+
+        let mut policy = create_policy(RedirectPolicyKind::FirstFit);
+        assert_eq!(
+            policy.select(4, &request(1), &stations, &cfg, &mut rng),
+            Some(1)
+        );
+
+        // only station 1 is active and has free resources
+        let mut policy = create_policy(RedirectPolicyKind::RoundRobin);
+        assert_eq!(
+            policy.select(0, &request(1), &stations, &cfg, &mut rng),
+            Some(1)
+        );
+        assert_eq!(
+            policy.select(0, &request(1), &stations, &cfg, &mut rng),
+            Some(1)
+        );
+
+        let mut policy = create_policy(RedirectPolicyKind::Random);
+        assert_eq!(
+            policy.select(0, &request(1), &stations, &cfg, &mut rng),
+            Some(1)
+        );
+
+        // station 3 is sleeping, stations 2 and 4 are full
+        let mut policy = create_policy(RedirectPolicyKind::Neighbour);
+        assert_eq!(
+            policy.select(2, &request(1), &stations, &cfg, &mut rng),
+            Some(1)
+        );
+        assert_eq!(
+            policy.select(0, &request(1), &stations, &cfg, &mut rng),
+            Some(1)
+        );
+        assert_eq!(
+            policy.select(3, &request(1), &stations, &cfg, &mut rng),
+            None
+        );
+
+        let mut policy = create_policy(RedirectPolicyKind::NoRedirect);
+        assert_eq!(
+            policy.select(0, &request(1), &stations, &cfg, &mut rng),
+            None
+        );
+    }
+
+    #[test]
+    fn multi_block_request() {
+        let cfg = Config {
+            resources_count: 10,
+            stations: vec![
+                StationConfig::default(),
+                StationConfig {
+                    resources_count: Some(2),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        // station 1 is the least loaded, but has only single free block
+        let stations = create_stations(&cfg, &[10, 1, 6, 8], &mut rng);
+        let mut policy = create_policy(RedirectPolicyKind::LeastLoaded);
+        assert_eq!(
+            policy.select(0, &request(1), &stations, &cfg, &mut rng),
+            Some(1)
+        );
+        assert_eq!(
+            policy.select(0, &request(2), &stations, &cfg, &mut rng),
+            Some(2)
+        );
+        let mut policy = create_policy(RedirectPolicyKind::Neighbour);
+        assert_eq!(
+            policy.select(2, &request(1), &stations, &cfg, &mut rng),
+            Some(1)
+        );
+        assert_eq!(
+            policy.select(2, &request(2), &stations, &cfg, &mut rng),
+            Some(3)
+        );
+        assert_eq!(
+            policy.select(2, &request(3), &stations, &cfg, &mut rng),
+            None
+        );
+    }
+
+    #[test]
+    fn topology() {
+        // station 0 is linked only with station 2
+        let mut topology = Topology::Adjacency {
+            neighbours: vec![vec![2], vec![], vec![], vec![]],
+        };
+        topology.prepare();
+        let cfg = Config {
+            resources_count: 2,
+            stations_count: 4,
+            topology,
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        let stations = create_stations(&cfg, &[2, 0, 1, 0], &mut rng);
+        for kind in [
+            RedirectPolicyKind::LeastLoaded,
+            RedirectPolicyKind::RoundRobin,
+            RedirectPolicyKind::Random,
+            RedirectPolicyKind::FirstFit,
+        ] {
+            let mut policy = create_policy(kind);
+            assert_eq!(
+                policy.select(0, &request(1), &stations, &cfg, &mut rng),
+                Some(2)
+            );
+            assert_eq!(
+                policy.select(3, &request(1), &stations, &cfg, &mut rng),
+                None
+            );
+        }
+        // station 1 is adjacent in ring, but not in topology
+        let mut policy = create_policy(RedirectPolicyKind::Neighbour);
+        assert_eq!(
+            policy.select(0, &request(1), &stations, &cfg, &mut rng),
+            None
+        );
+    }
+
+    #[test]
+    fn coverage() {
+        // stations are neighbours, because their coverage areas overlap
+        let position = |x: f64, y: f64| Position { x, y };
+        let mut topology = Topology::Coverage {
+            positions: vec![position(0.0, 0.0), position(150.0, 0.0)],
+            radius: 100.0,
+            neighbours: Vec::new(),
+        };
+        topology.prepare();
+        let cfg = Config {
+            resources_count: 2,
+            stations_count: 2,
+            topology,
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        let stations = create_stations(&cfg, &[2, 0], &mut rng);
+        let outside = request(1).with_position(-50.0, 0.0);
+        let overlap = request(1).with_position(75.0, 0.0);
+        for kind in [
+            RedirectPolicyKind::LeastLoaded,
+            RedirectPolicyKind::RoundRobin,
+            RedirectPolicyKind::Random,
+            RedirectPolicyKind::FirstFit,
+            RedirectPolicyKind::Neighbour,
+        ] {
+            let mut policy = create_policy(kind);
+            // station 1 is in range of station 0, but does not cover user at the far side
+            assert_eq!(policy.select(0, &outside, &stations, &cfg, &mut rng), None);
+            assert_eq!(
+                policy.select(0, &overlap, &stations, &cfg, &mut rng),
+                Some(1)
+            );
+        }
+    }
+
+    #[test]
+    fn round_robin_order() {
+        let cfg = Config {
+            resources_count: 2,
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        let stations = create_stations(&cfg, &[0, 2, 1, 0], &mut rng);
+        let mut policy = create_policy(RedirectPolicyKind::RoundRobin);
+        let selected: Vec<Option<usize>> = (0..4)
+            .map(|_| policy.select(1, &request(1), &stations, &cfg, &mut rng))
+            .collect();
+        assert_eq!(selected, vec![Some(2), Some(3), Some(0), Some(2)]);
+    }
+}
@@ -1,22 +1,50 @@
-use clap::Parser;
 use rand::prelude::*;
 use rand::SeedableRng;
 use rayon::prelude::*;
+use std::collections::VecDeque;
 use std::ffi::OsString;
 use std::io::Write;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use crate::basestation::{BaseStation, BaseStationEvent, BaseStationResult, BaseStationState};
-use crate::config::WalkOverType;
+use crate::error::CassidyError;
 use crate::logger::Logger;
+use crate::mobility::Movement;
+use crate::queue::Queues;
+use crate::redirect::{create_policy, RedirectPolicy};
+use crate::sleep::{
+    create_controller, offload_targets, plan_offload, PowerDecision, SleepController,
+};
+use crate::spatial::{
+    covers_user, handover_target, is_covered, nearest_station, spawn_position, SpatialSource,
+};
+use crate::stats::mser_truncation;
+use crate::trace::TraceRecord;
 use crate::{
-    config::{Cli, Config},
+    config::{Config, LambdaProfile, Topology},
     user::User,
 };
 
+mod builder;
+mod scheduler;
 mod sim_results;
+pub use builder::*;
+pub use scheduler::*;
 pub use sim_results::*;
 
+/// Number of system usage samples collected by pilot run of automatic warm-up detection
+const WARMUP_SAMPLES: u64 = 5000;
+
+/// Source of the next event processed by simulation loop
+#[derive(Debug, Clone, Copy)]
+enum EventSource {
+    Station,
+    Spatial,
+    Queue,
+    Lambda,
+}
+
 #[derive(Debug)]
 pub struct SimState {
     pub time: u64,
@@ -27,11 +55,24 @@ pub struct SimState {
     pub all_users: usize,
     pub redirected_users: usize,
     pub dropped_users: usize,
+    pub neighbour_blocked_users: usize,
+    pub uncovered_users: usize,
+    pub handovers: usize,
+    pub dropped_calls: usize,
+    pub preempted_dropped: usize,
+    pub admission_blocked: usize,
+    pub class_users: Vec<usize>,
+    pub class_dropped: Vec<usize>,
+    pub priority_users: Vec<usize>,
+    pub priority_admitted: Vec<usize>,
+    pub priority_preempted: Vec<usize>,
+    pub priority_dropped: Vec<usize>,
 }
 
 impl SimState {
     pub fn new(cfg: &Config) -> SimState {
         let idx: usize = if cfg.lambda_coefs.len() > 1 { 1 } else { 0 };
+        let levels = cfg.priorities.as_ref().map_or(0, |p| p.shares.len());
         SimState {
             time: 0,
             next_user_id: 0,
@@ -41,70 +82,106 @@ impl SimState {
             all_users: 0,
             redirected_users: 0,
             dropped_users: 0,
+            neighbour_blocked_users: 0,
+            uncovered_users: 0,
+            handovers: 0,
+            dropped_calls: 0,
+            preempted_dropped: 0,
+            admission_blocked: 0,
+            class_users: vec![0; cfg.service_classes.len()],
+            class_dropped: vec![0; cfg.service_classes.len()],
+            priority_users: vec![0; levels],
+            priority_admitted: vec![0; levels],
+            priority_preempted: vec![0; levels],
+            priority_dropped: vec![0; levels],
         }
     }
 }
 
 pub struct SimContainer {
-    cli: Cli,
+    options: SimOptions,
     cfg: Config,
+    trace: Option<Arc<Vec<TraceRecord>>>,
 }
 
 impl SimContainer {
-    pub fn new() -> Result<SimContainer, String> {
-        let cli = Cli::parse().validate()?;
-        let mut cfg = cli.create_config()?.validate()?;
-        // convert lambda timestamps from hours to microseconds
-        for p in cfg.lambda_coefs.iter_mut() {
-            p.time *= 3600.0 * 1000_000.0;
-        }
-        Ok(SimContainer { cli, cfg })
-    }
-
-    pub fn update_param(&mut self, param: &WalkOverType, value: f64) {
-        match param {
-            WalkOverType::Lambda => {
-                self.cfg.lambda = value;
-            }
-            WalkOverType::SleepLow => {
-                self.cfg.sleep_threshold = value;
-            }
-            WalkOverType::SleepHigh => {
-                self.cfg.wakeup_threshold = value;
-            }
-        }
+    pub fn simulate(&self, iter: u32, log_path: PathBuf) -> Result<SimResults, CassidyError> {
+        // convert warm-up from hours to microseconds
+        let warmup = (self.cfg.warmup * 3600.0 * 1_000_000.0) as u64;
+        self.simulate_with_warmup(iter, log_path, warmup, None)
     }
 
-    pub fn simulate(&self, iter: u32, log_path: PathBuf) -> SimResults {
+    /// Runs single simulation, where statistics are recorded only after warm-up period [us].
+    /// If `usage_series` is given, system usage is sampled into it in equal time intervals.
+    fn simulate_with_warmup(
+        &self,
+        iter: u32,
+        log_path: PathBuf,
+        warmup: u64,
+        mut usage_series: Option<&mut Vec<f64>>,
+    ) -> Result<SimResults, CassidyError> {
         // initialize state
         let mut sim_state = SimState::new(&self.cfg);
-        let mut rng = match &self.cli.seed {
+        let mut rng = match &self.options.seed {
             Some(seed) => StdRng::seed_from_u64(seed + iter as u64),
             None => StdRng::from_entropy(),
         };
-        let mut logger = Logger::new(self.cli.log, &self.cfg, &log_path)
-            .expect("Internal error: failed to create log file");
+        let mut logger = Logger::new(self.options.log, &self.cfg, &log_path)?;
         // create BaseStations
         let mut stations: Vec<BaseStation> = Vec::with_capacity(self.cfg.stations_count);
         for i in 0..self.cfg.stations_count {
             stations.push(BaseStation::new(i, &self.cfg, sim_state.lambda, &mut rng));
         }
+        if let Some(trace) = &self.trace {
+            let mut arrivals = vec![VecDeque::new(); stations.len()];
+            for record in trace.iter() {
+                arrivals[record.station].push_back(*record);
+            }
+            for (station, records) in stations.iter_mut().zip(arrivals) {
+                station.replay(records);
+            }
+        }
+        // in spatial mode users are generated for the whole network instead of single stations
+        let mut spatial = match &self.cfg.spatial {
+            Some(_) => {
+                for station in stations.iter_mut() {
+                    station.next_user_add = u64::MAX;
+                }
+                Some(SpatialSource::new(&self.cfg, sim_state.lambda, &mut rng))
+            }
+            None => None,
+        };
+        let mut queues = self
+            .cfg
+            .queue
+            .as_ref()
+            .map(|queue| Queues::new(queue, self.cfg.stations_count));
         // turn all but first station to sleep if enabled
-        if self.cli.enable_sleep {
+        if self.options.enable_sleep {
             for station in stations.iter_mut().skip(1) {
-                station.state = BaseStationState::Sleep;
+                station.state = BaseStationState::Sleep(0);
             }
         }
+        let mut scheduler = EventScheduler::new(self.options.scheduler, &stations);
+        let mut redirect_policy = create_policy(self.cfg.redirect_policy);
+        let mut sleep_controller = create_controller(&self.cfg.sleep_controller, &self.cfg);
         // convert end time from hours to microseconds
-        let end_time = (self.cli.duration * 3600.0 * 1000_000.0) as u64;
+        let end_time = (self.options.duration * 3600.0 * 1_000_000.0) as u64;
+        let mut warmed_up = warmup == 0;
+        let sample_period = (end_time / WARMUP_SAMPLES).max(1);
+        let mut next_sample = 0;
 
         // initialize binary logger
         let mut sample_counter = 0;
         let mut bin_path = log_path.clone();
         bin_path.set_file_name("sim_bin");
-        bin_path.set_extension(log_path.extension().unwrap());
-        let mut wave_file = if self.cli.log_wave {
-            let mut wave_file = std::fs::File::create(bin_path).unwrap();
+        bin_path.set_extension(log_path.extension().unwrap_or_default());
+        let mut wave_file = if self.options.log_wave {
+            let mut wave_file =
+                std::fs::File::create(&bin_path).map_err(|source| CassidyError::Io {
+                    path: bin_path.clone(),
+                    source,
+                })?;
             let _ = wave_file.write(&(self.cfg.stations_count as u32).to_le_bytes());
             Some(wave_file)
         } else {
@@ -113,77 +190,250 @@ impl SimContainer {
 
         // simulation loop
         while sim_state.time < end_time {
-            // update lambda
-            if sim_state.time >= sim_state.lambda_update_time {
-                let l_next = &self.cfg.lambda_coefs[sim_state.lambda_update_idx];
-                sim_state.lambda = self.cfg.lambda * l_next.coef;
-                sim_state.lambda_update_time = sim_state.time + l_next.time as u64;
-                sim_state.lambda_update_idx =
-                    (sim_state.lambda_update_idx + 1) % self.cfg.lambda_coefs.len();
-                if self.cli.log {
-                    logger.log(
-                        format!("Lambda updated to: {}", sim_state.lambda),
-                        sim_state.time,
-                        &self.cfg,
-                    );
+            // get next event
+            let (mut next_event_time, mut event_station) = scheduler.next(&stations)?;
+            let (_, next_event) = stations[event_station].get_next_event();
+            let mut event_source = EventSource::Station;
+            if let Some(source) = &spatial {
+                if source.next_event() < next_event_time {
+                    next_event_time = source.next_event();
+                    event_source = EventSource::Spatial;
                 }
             }
-
-            // get next event
-            let mut event_station: usize = 0;
-            let (mut next_event_time, mut next_event) = stations[0].get_next_event();
-            for (i, station) in stations[1..].iter().enumerate() {
-                let (time, event) = station.get_next_event();
-                if time < next_event_time {
-                    next_event_time = time;
-                    next_event = event;
-                    event_station = i + 1; // offset from iteration range
+            if let Some(queues) = &queues {
+                if queues.next_deadline() < next_event_time {
+                    next_event_time = queues.next_deadline();
+                    event_source = EventSource::Queue;
                 }
             }
+            // lambda of step profile changes at boundaries of its phases,
+            // before any other event at the same time. Other profiles are evaluated by stations
+            if self.cfg.lambda_profile == LambdaProfile::Step
+                && sim_state.lambda_update_time <= next_event_time
+            {
+                next_event_time = sim_state.lambda_update_time;
+                event_source = EventSource::Lambda;
+            }
 
             // update time counter
             if next_event_time < sim_state.time {
-                panic!("Internal error: next event timestamp < current timestamp");
+                return Err(CassidyError::Internal(format!(
+                    "next event timestamp {} < current timestamp {}",
+                    next_event_time, sim_state.time
+                )));
             }
-            // Accumulate and exit early if next event exceeds simulation duration
-            if next_event_time > end_time {
-                let dt = end_time - sim_state.time;
+            if let Some(series) = usage_series.as_mut() {
+                while next_sample <= next_event_time.min(end_time) {
+                    series.push(Self::system_usage(&stations));
+                    next_sample += sample_period;
+                }
+            }
+            // Discard statistics gathered during warm-up period
+            if !warmed_up && next_event_time >= warmup {
+                warmed_up = true;
                 for station in stations.iter_mut() {
-                    station.accumulate_counters(dt, &self.cfg);
+                    station.update_counters(warmup);
+                    station.reset_counters();
+                }
+                sim_state.all_users = 0;
+                sim_state.redirected_users = 0;
+                sim_state.dropped_users = 0;
+                sim_state.neighbour_blocked_users = 0;
+                sim_state.uncovered_users = 0;
+                sim_state.handovers = 0;
+                sim_state.dropped_calls = 0;
+                sim_state.preempted_dropped = 0;
+                sim_state.admission_blocked = 0;
+                sim_state.class_users.fill(0);
+                sim_state.class_dropped.fill(0);
+                sim_state.priority_users.fill(0);
+                sim_state.priority_admitted.fill(0);
+                sim_state.priority_preempted.fill(0);
+                sim_state.priority_dropped.fill(0);
+                if let Some(queues) = queues.as_mut() {
+                    queues.update_counters(warmup);
+                    queues.reset_counters();
                 }
+                if self.options.log {
+                    logger.log("Warm-up period finished".to_string(), warmup, &self.cfg);
+                }
+            }
+            // Exit early if next event exceeds simulation duration
+            if next_event_time > end_time {
                 break;
             }
-            let dt = next_event_time - sim_state.time;
             sim_state.time = next_event_time;
 
-            // Update accumulators
-            for station in stations.iter_mut() {
-                station.accumulate_counters(dt, &self.cfg);
-            }
-            match next_event {
-                BaseStationEvent::AddUser => sim_state.all_users += 1,
-                _ => (),
-            }
+            let res = match event_source {
+                EventSource::Lambda => {
+                    let l_next = &self.cfg.lambda_coefs[sim_state.lambda_update_idx];
+                    sim_state.lambda = self.cfg.lambda * l_next.coef;
+                    sim_state.lambda_update_time += l_next.time as u64;
+                    sim_state.lambda_update_idx =
+                        (sim_state.lambda_update_idx + 1) % self.cfg.lambda_coefs.len();
+                    // arrivals are not generated at zero lambda, so they start again after change
+                    match spatial.as_mut() {
+                        Some(source) if source.next_arrival == u64::MAX => {
+                            source.advance(sim_state.time, sim_state.lambda, &self.cfg, &mut rng)
+                        }
+                        Some(_) => {}
+                        None => {
+                            for station in stations.iter_mut() {
+                                station.resume_arrivals(
+                                    sim_state.time,
+                                    sim_state.lambda,
+                                    &self.cfg,
+                                    &mut rng,
+                                );
+                            }
+                        }
+                    }
+                    if self.options.log {
+                        logger.log(
+                            format!("Lambda updated to: {}", sim_state.lambda),
+                            sim_state.time,
+                            &self.cfg,
+                        );
+                    }
+                    None
+                }
+                EventSource::Queue => {
+                    if let Some(queues) = queues.as_mut() {
+                        self.abandon_queue(queues, &sim_state, &mut logger);
+                    }
+                    None
+                }
+                EventSource::Spatial => match spatial.as_mut() {
+                    Some(source) if source.next_handover < source.next_arrival => {
+                        let modified = self.handover(
+                            source,
+                            &mut sim_state,
+                            &mut stations,
+                            &mut rng,
+                            &mut logger,
+                        );
+                        // users leaving their stations free resources for waiting users
+                        for idx in modified {
+                            if let Some(queues) = queues.as_mut() {
+                                self.admit_queued(
+                                    queues,
+                                    &sim_state,
+                                    &mut stations[idx],
+                                    &mut logger,
+                                );
+                            }
+                            scheduler.update(idx, &stations);
+                        }
+                        None
+                    }
+                    Some(source) => {
+                        sim_state.all_users += 1;
+                        match self.spatial_arrival(
+                            source,
+                            &mut sim_state,
+                            &mut stations,
+                            &mut rng,
+                            &mut logger,
+                        ) {
+                            Some((idx, res)) => {
+                                event_station = idx;
+                                res
+                            }
+                            None => None,
+                        }
+                    }
+                    None => None,
+                },
+                EventSource::Station => {
+                    // Update accumulators of station that will be modified
+                    stations[event_station].update_counters(sim_state.time);
+                    if let BaseStationEvent::AddUser = next_event {
+                        sim_state.all_users += 1;
+                    }
 
-            // execute event
-            let res = stations[event_station].execute_event(
-                &next_event,
-                &self.cfg,
-                &mut sim_state,
-                &mut rng,
-                self.cli.log,
-                &mut logger,
-            );
+                    // execute event
+                    let res = stations[event_station].execute_event(
+                        &next_event,
+                        &self.cfg,
+                        &mut sim_state,
+                        &mut rng,
+                        self.options.log,
+                        &mut logger,
+                    )?;
+                    // freed resources are taken by waiting users
+                    if let (
+                        Some(queues),
+                        BaseStationEvent::ReleaseUser | BaseStationEvent::PowerUp,
+                    ) = (queues.as_mut(), &next_event)
+                    {
+                        self.admit_queued(
+                            queues,
+                            &sim_state,
+                            &mut stations[event_station],
+                            &mut logger,
+                        );
+                    }
+                    res
+                }
+            };
+            // blocked arrival with high priority takes resources of user with lower priority,
+            // who is then redirected instead
+            let (res, arrival) = match res {
+                Some(user) if self.cfg.priorities.as_ref().is_some_and(|p| p.preemption) => {
+                    let user_id = user.id;
+                    match stations[event_station].preempt(user, &mut sim_state) {
+                        Ok(victim) => {
+                            if self.options.log {
+                                logger.log(
+                                    format!(
+                                        "User id: {} preempted by User id: {} at Station id: {}",
+                                        victim.id, user_id, event_station
+                                    ),
+                                    sim_state.time,
+                                    &self.cfg,
+                                )
+                            }
+                            // victim may occupy more blocks than user who took its place
+                            if let Some(queues) = queues.as_mut() {
+                                self.admit_queued(
+                                    queues,
+                                    &sim_state,
+                                    &mut stations[event_station],
+                                    &mut logger,
+                                );
+                            }
+                            (Some(victim), false)
+                        }
+                        Err(user) => (Some(user), true),
+                    }
+                }
+                res => (res, true),
+            };
+            scheduler.update(event_station, &stations);
 
             // handle possible redirections
             if let Some(user) = res {
                 let redirected_user_id = user.id;
-                let res = self.redirect(user, &mut stations);
+                let priority = user.priority;
+                let res = self.redirect(
+                    user,
+                    event_station,
+                    &sim_state,
+                    redirect_policy.as_mut(),
+                    &mut rng,
+                    &mut stations,
+                );
                 match res {
                     Ok(to_station_id) => {
+                        scheduler.update(to_station_id, &stations);
                         sim_state.redirected_users += 1;
+                        if arrival {
+                            if let Some(count) = sim_state.priority_admitted.get_mut(priority) {
+                                *count += 1;
+                            }
+                        }
                         let from_station_id = stations[event_station].id;
-                        if self.cli.log {
+                        if self.options.log {
                             logger.log(
                                 format!(
                                     "Redirect\tUser id: {} from Station id: {} to Station id: {}",
@@ -194,229 +444,632 @@ impl SimContainer {
                             )
                         }
                     }
-                    Err(_) => {
-                        sim_state.dropped_users += 1;
-                        if self.cli.log {
-                            logger.log(
-                                format!("User id: {} dropped", redirected_user_id),
-                                sim_state.time,
-                                &self.cfg,
-                            )
+                    Err(user) => {
+                        // user waits for free resources, if there is place in the queue
+                        let res = match queues.as_mut() {
+                            Some(queues) => {
+                                self.enqueue(queues, event_station, user, &sim_state, &mut rng)
+                            }
+                            None => Err(user),
+                        };
+                        match res {
+                            Ok(()) => {
+                                if self.options.log {
+                                    logger.log(
+                                        format!("User id: {} queued", redirected_user_id),
+                                        sim_state.time,
+                                        &self.cfg,
+                                    )
+                                }
+                            }
+                            Err(_) if !arrival => {
+                                // forced termination is not counted as blocked arrival
+                                sim_state.preempted_dropped += 1;
+                                if self.options.log {
+                                    logger.log(
+                                        format!(
+                                            "User id: {} dropped after preemption",
+                                            redirected_user_id
+                                        ),
+                                        sim_state.time,
+                                        &self.cfg,
+                                    )
+                                }
+                            }
+                            Err(user) => {
+                                sim_state.dropped_users += 1;
+                                let topology = &self.cfg.topology;
+                                let neighbour_free = stations.iter().any(|s| {
+                                    s.id != event_station
+                                        && topology.is_neighbour(event_station, s.id)
+                                        && s.is_active()
+                                        && s.can_serve(user.blocks)
+                                });
+                                if !neighbour_free {
+                                    sim_state.neighbour_blocked_users += 1;
+                                }
+                                if let Some(class) = user.class {
+                                    sim_state.class_dropped[class] += 1;
+                                }
+                                if let Some(count) = sim_state.priority_dropped.get_mut(priority) {
+                                    *count += 1;
+                                }
+                                if self.options.log {
+                                    logger.log(
+                                        format!("User id: {} dropped", redirected_user_id),
+                                        sim_state.time,
+                                        &self.cfg,
+                                    )
+                                }
+                            }
                         }
                     }
                 };
             }
 
             // check for potential power-up/down of stations
-            if self.cli.enable_sleep {
-                self.power_up_down(&sim_state, &mut stations);
+            if self.options.enable_sleep {
+                let modified =
+                    self.power_up_down(&sim_state, sleep_controller.as_mut(), &mut stations);
+                // stations offloaded by wake-up have free resources for waiting users
+                for idx in modified {
+                    if let Some(queues) = queues.as_mut() {
+                        self.admit_queued(queues, &sim_state, &mut stations[idx], &mut logger);
+                    }
+                    scheduler.update(idx, &stations);
+                }
             }
 
             // Binary logging
-            match wave_file.as_mut() {
-                Some(file) => {
-                    // each "row" of data consists of:
-                    // - timestamp 8 bytes
-                    // - stations data:
-                    //   - usage 4 bytes
-                    //   - state 1 byte
-                    sample_counter += 1;
-                    if sample_counter >= self.cli.samples {
-                        sample_counter = 0;
-                        let mut data = Vec::from(sim_state.time.to_le_bytes());
-                        for station in stations.iter() {
-                            for byte in (station.get_usage_raw() as u32).to_le_bytes() {
-                                data.push(byte)
-                            }
-                            let state_code: u8 = match station.state {
-                                BaseStationState::Active => 4,
-                                BaseStationState::Sleep => 1,
-                                BaseStationState::PowerUp(_) => 3,
-                                BaseStationState::PowerDown(_) => 2,
-                            };
-                            data.push(state_code);
+            if let Some(file) = wave_file.as_mut() {
+                // each "row" of data consists of:
+                // - timestamp 8 bytes
+                // - stations data:
+                //   - usage 4 bytes
+                //   - state 1 byte
+                sample_counter += 1;
+                if sample_counter >= self.options.samples {
+                    sample_counter = 0;
+                    let mut data = Vec::from(sim_state.time.to_le_bytes());
+                    for station in stations.iter() {
+                        for byte in (station.get_usage_raw() as u32).to_le_bytes() {
+                            data.push(byte)
                         }
-                        let _ = file.write_all(&data);
+                        let state_code: u8 = match station.state {
+                            BaseStationState::Active => 4,
+                            BaseStationState::Sleep(_) => 1,
+                            BaseStationState::PowerUp(_) => 3,
+                            BaseStationState::PowerDown(_) => 2,
+                        };
+                        data.push(state_code);
                     }
+                    let _ = file.write_all(&data);
                 }
-                None => (),
-            };
+            }
         }
         logger.flush();
+        for station in stations.iter_mut() {
+            station.update_counters(end_time);
+        }
+        if let Some(queues) = queues.as_mut() {
+            queues.update_counters(end_time);
+        }
+        if let Some(series) = usage_series {
+            while next_sample <= end_time {
+                series.push(Self::system_usage(&stations));
+                next_sample += sample_period;
+            }
+        }
         // return results
         let mut stations_results: Vec<BaseStationResult> = Vec::new();
         let mut avg_usage = 0.0;
         let mut avg_power = 0.0;
+        let mut total_energy = 0.0;
+        let mut total_resources = 0;
         for station in stations.iter() {
-            let res = station.get_results(end_time);
-            avg_usage += res.average_usage;
+            let res = station.get_results(end_time - warmup);
+            // weight usage by station size, so it is usage of all resources in the system
+            avg_usage += res.average_usage * station.params.resources_count as f64;
+            total_resources += station.params.resources_count;
             avg_power += res.average_power;
+            total_energy += res.energy;
             stations_results.push(res);
         }
-        SimResults {
-            average_usage: avg_usage / self.cfg.stations_count as f64,
+        let classes = self
+            .cfg
+            .service_classes
+            .iter()
+            .enumerate()
+            .map(|(i, class)| ClassResult {
+                name: class.name.clone(),
+                total_users: sim_state.class_users[i],
+                dropped_users: sim_state.class_dropped[i],
+                // class with small share can get no arrivals in short simulation
+                blocking_rate: if sim_state.class_users[i] == 0 {
+                    0.0
+                } else {
+                    sim_state.class_dropped[i] as f64 / sim_state.class_users[i] as f64 * 100.0
+                },
+            })
+            .collect();
+        let priorities = (0..sim_state.priority_users.len())
+            .map(|i| PriorityResult {
+                total_users: sim_state.priority_users[i],
+                admitted_users: sim_state.priority_admitted[i],
+                preempted_users: sim_state.priority_preempted[i],
+                dropped_users: sim_state.priority_dropped[i],
+                drop_rate: if sim_state.priority_users[i] == 0 {
+                    0.0
+                } else {
+                    sim_state.priority_dropped[i] as f64 / sim_state.priority_users[i] as f64
+                        * 100.0
+                },
+            })
+            .collect();
+        let account = |schedule: &Option<Vec<f64>>, unit: f64| {
+            schedule.as_ref().map(|schedule| {
+                let priced: Vec<(f64, f64)> =
+                    stations.iter().map(|s| s.priced_energy(schedule)).collect();
+                let stations: Vec<f64> = priced.iter().map(|(value, _)| value * unit).collect();
+                let baseline: f64 = priced.iter().map(|(_, value)| value * unit).sum();
+                AccountResult::new(stations.iter().sum(), stations, baseline)
+            })
+        };
+        // carbon intensity is given in [g CO2 / kWh], emissions are reported in [kg CO2]
+        let cost = account(&self.cfg.tariff, 1.0);
+        let emissions = account(&self.cfg.carbon_intensity, 0.001);
+        let mobile = self.cfg.spatial.as_ref().is_some_and(|s| s.is_mobile());
+        Ok(SimResults {
+            average_usage: avg_usage / total_resources as f64,
             average_drop_rate: (sim_state.dropped_users as f64) / (sim_state.all_users as f64)
                 * 100.0,
             average_power: avg_power / self.cfg.stations_count as f64,
+            total_energy: total_energy / 1000.0,
             total_users: sim_state.all_users,
             dropped_users: sim_state.dropped_users,
+            neighbour_blocked_users: (self.cfg.topology != Topology::Full)
+                .then_some(sim_state.neighbour_blocked_users),
+            uncovered_users: self
+                .cfg
+                .spatial
+                .is_some()
+                .then_some(sim_state.uncovered_users),
+            handovers: mobile.then_some(sim_state.handovers),
+            dropped_calls: mobile.then_some(sim_state.dropped_calls),
+            preempted_dropped: self
+                .cfg
+                .priorities
+                .as_ref()
+                .is_some_and(|p| p.preemption)
+                .then_some(sim_state.preempted_dropped),
+            admission_blocked: self
+                .cfg
+                .has_admission_control()
+                .then_some(sim_state.admission_blocked),
+            queue: queues.map(|queues| QueueResult {
+                queued_users: queues.queued_users,
+                abandoned_users: queues.abandoned_users,
+                // no user has to wait under light load
+                abandonment_rate: if queues.queued_users == 0 {
+                    0.0
+                } else {
+                    queues.abandoned_users as f64 / queues.queued_users as f64 * 100.0
+                },
+                average_wait: if queues.admitted_users == 0 {
+                    0.0
+                } else {
+                    queues.total_wait as f64 / queues.admitted_users as f64 / 1000.0
+                },
+                average_queue_length: queues.length_integral / (end_time - warmup) as f64,
+            }),
+            cost,
+            emissions,
             stations: stations_results,
+            sleep_levels: self
+                .cfg
+                .sleep_levels
+                .iter()
+                .map(|l| l.name.clone())
+                .collect(),
+            classes,
+            priorities,
+            spread: None,
+            detected_warmup: None,
+        })
+    }
+
+    /// Usage [%] of all resources in the system
+    fn system_usage(stations: &[BaseStation]) -> f64 {
+        let used: usize = stations.iter().map(|s| s.get_usage_raw()).sum();
+        let total: usize = stations.iter().map(|s| s.params.resources_count).sum();
+        used as f64 / total as f64 * 100.0
+    }
+
+    /// Estimates length of warm-up period [us] with MSER-5 rule applied to system usage
+    /// of single pilot simulation.
+    fn detect_warmup(&self) -> Result<u64, CassidyError> {
+        let pilot = SimContainer {
+            options: SimOptions {
+                log: false,
+                log_wave: false,
+                ..self.options.clone()
+            },
+            cfg: self.cfg.clone(),
+            trace: self.trace.clone(),
+        };
+        let mut series = Vec::new();
+        let log_path = self.options.log_dir.join("sim.pilot");
+        pilot.simulate_with_warmup(0, log_path, 0, Some(&mut series))?;
+        let end_time = (self.options.duration * 3600.0 * 1_000_000.0) as u64;
+        let sample_period = (end_time / WARMUP_SAMPLES).max(1);
+        Ok(mser_truncation(&series, 5) as u64 * sample_period)
+    }
+
+    /// Creates user at random position and attaches it to the nearest awake station covering it.
+    /// Returns index of that station, with user if the station could not serve it.
+    /// Users outside coverage of awake stations are dropped and None is returned
+    fn spatial_arrival(
+        &self,
+        source: &mut SpatialSource,
+        sim_state: &mut SimState,
+        stations: &mut [BaseStation],
+        rng: &mut StdRng,
+        logger: &mut Logger,
+    ) -> Option<(usize, Option<User>)> {
+        let spatial = self.cfg.spatial.as_ref()?;
+        let position = spawn_position(spatial, rng);
+        source.advance(sim_state.time, sim_state.lambda, &self.cfg, rng);
+        let mut user = User::new(sim_state.next_user_id, sim_state.time, rng, &self.cfg);
+        match nearest_station(position, stations, &self.cfg) {
+            Some(idx) => {
+                // position is kept also by static users, so they are moved only to stations covering them
+                user.movement = Some(Movement::new(position, sim_state.time, spatial, rng));
+                let station = &mut stations[idx];
+                station.update_counters(sim_state.time);
+                let res = station.add_user(user, &self.cfg, sim_state, self.options.log, logger);
+                Some((idx, res))
+            }
+            None => {
+                sim_state.next_user_id += 1;
+                sim_state.dropped_users += 1;
+                sim_state.uncovered_users += 1;
+                if let Some(class) = user.class {
+                    sim_state.class_users[class] += 1;
+                    sim_state.class_dropped[class] += 1;
+                }
+                if let Some(count) = sim_state.priority_users.get_mut(user.priority) {
+                    *count += 1;
+                    sim_state.priority_dropped[user.priority] += 1;
+                }
+                if self.options.log {
+                    logger.log(
+                        format!(
+                            "User id: {} dropped, position ({:.1}, {:.1}) is not covered",
+                            user.id, position.x, position.y
+                        ),
+                        sim_state.time,
+                        &self.cfg,
+                    )
+                }
+                None
+            }
         }
     }
 
-    /// Takes single user and assigns it to active station with lowest usage
-    fn redirect(&self, user: User, stations: &mut Vec<BaseStation>) -> Result<usize, ()> {
-        let redirect_station = stations
-            .iter_mut()
-            .filter(|x| match x.state {
-                BaseStationState::Active => true,
-                _ => false,
-            })
-            .min_by(|x, y| {
-                x.get_usage(&self.cfg)
-                    .partial_cmp(&y.get_usage(&self.cfg))
-                    .unwrap()
-            })
-            .unwrap();
-        redirect_station.redirect_here(&self.cfg, user)?;
-        Ok(redirect_station.id)
+    /// Updates positions of moving users. Users, which left coverage of their station, are handed over
+    /// to the nearest active station covering them, which has enough free resources.
+    /// If there is no such station, user is dropped and counted as dropped call.
+    /// Returns indexes of all stations modified in the process
+    fn handover(
+        &self,
+        source: &mut SpatialSource,
+        sim_state: &mut SimState,
+        stations: &mut [BaseStation],
+        rng: &mut StdRng,
+        logger: &mut Logger,
+    ) -> Vec<usize> {
+        let Some(spatial) = self.cfg.spatial.as_ref() else {
+            return Vec::new();
+        };
+        source.next_handover = sim_state.time + spatial.handover_interval * 1000;
+        let mut leaving = Vec::new();
+        for station in stations.iter_mut().filter(|s| s.is_active()) {
+            let idx = station.id;
+            station.update_counters(sim_state.time);
+            let users = station.release_where(|user| match user.movement.as_mut() {
+                Some(movement) => {
+                    movement.advance(sim_state.time, spatial, rng);
+                    !is_covered(movement.position, idx, &self.cfg)
+                }
+                None => false,
+            });
+            leaving.extend(users.into_iter().map(|user| (idx, user)));
+        }
+        let mut modified: Vec<usize> = leaving.iter().map(|(idx, _)| *idx).collect();
+        for (from, user) in leaving {
+            let user_id = user.id;
+            // only moving users leave their stations
+            let Some(movement) = user.movement else {
+                continue;
+            };
+            match handover_target(movement.position, user.blocks, stations, &self.cfg) {
+                Some(to) => {
+                    let _ = stations[to].redirect_here(user);
+                    sim_state.handovers += 1;
+                    modified.push(to);
+                    if self.options.log {
+                        logger.log(
+                            format!(
+                                "Handover\tUser id: {} from Station id: {} to Station id: {}",
+                                user_id, from, to
+                            ),
+                            sim_state.time,
+                            &self.cfg,
+                        )
+                    }
+                }
+                None => {
+                    sim_state.dropped_calls += 1;
+                    if self.options.log {
+                        logger.log(
+                            format!(
+                                "User id: {} dropped during handover from Station id: {}",
+                                user_id, from
+                            ),
+                            sim_state.time,
+                            &self.cfg,
+                        )
+                    }
+                }
+            }
+        }
+        modified.sort_unstable();
+        modified.dedup();
+        modified
     }
 
-    fn power_up_down(&self, sim_state: &SimState, stations: &mut Vec<BaseStation>) {
-        let heavy_load = stations
-            .iter()
-            .position(|x| x.get_usage(&self.cfg) >= self.cfg.wakeup_threshold);
-        match heavy_load {
-            Some(idx) => self.try_wakeup(sim_state, idx, stations),
-            None => self.try_shutdown(sim_state, stations),
+    /// Puts user, who could not be served by any station, into waiting queue with random patience.
+    /// If the queue is full, user is returned back
+    fn enqueue(
+        &self,
+        queues: &mut Queues,
+        station: usize,
+        user: User,
+        sim_state: &SimState,
+        rng: &mut StdRng,
+    ) -> Result<(), User> {
+        let patience = match self.cfg.queue.as_ref().and_then(|q| q.patience.as_ref()) {
+            Some(patience) => User::draw_service_time(patience, 0, 0, rng),
+            None => u64::MAX,
         };
+        queues.push(station, user, sim_state.time, patience)
+    }
+
+    /// Removes users, whose patience expired, from waiting queues
+    fn abandon_queue(&self, queues: &mut Queues, sim_state: &SimState, logger: &mut Logger) {
+        for queued in queues.abandon(sim_state.time) {
+            if self.options.log {
+                logger.log(
+                    format!("User id: {} abandoned queue", queued.user.id),
+                    sim_state.time,
+                    &self.cfg,
+                )
+            }
+        }
     }
 
-    fn try_wakeup(
+    /// Admits waiting users into given station, while it has free resources.
+    /// Must be called whenever usage of active station drops
+    fn admit_queued(
         &self,
+        queues: &mut Queues,
         sim_state: &SimState,
-        heavy_load_idx: usize,
-        stations: &mut Vec<BaseStation>,
+        station: &mut BaseStation,
+        logger: &mut Logger,
     ) {
-        // Assumption -> wakeup station is empty or has very little users registered
-        // so there is need for only one redirection
-        stations
-            .iter()
-            .position(|x| match x.state {
-                BaseStationState::Sleep => true,
-                _ => false,
-            })
-            .map(|idx| {
-                // Redirect half of load to woken up station
-                let mut users = stations[heavy_load_idx].release_half();
-                for user in users.iter_mut() {
-                    user.end += self.cfg.wakeup_delay * 1000;
-                }
-                stations[idx].state =
-                    BaseStationState::PowerUp(sim_state.time + self.cfg.wakeup_delay * 1000);
-                let u_len = users.len();
-                stations[idx].redirect_here_vec(&self.cfg, &mut users, u_len);
-                debug_assert!(users.len() == 0);
-            });
+        station.update_counters(sim_state.time);
+        for id in queues.admit(station, sim_state.time) {
+            if self.options.log {
+                logger.log(
+                    format!(
+                        "User id: {} admitted from queue to Station id: {}",
+                        id, station.id
+                    ),
+                    sim_state.time,
+                    &self.cfg,
+                )
+            }
+        }
     }
 
-    fn try_shutdown(&self, sim_state: &SimState, stations: &mut Vec<BaseStation>) {
-        // Find station with usage below sleep_threshold
-        let shutdown_station_id = stations
-            .iter()
-            .filter(|s| s.is_active())
-            .find(|s| s.get_usage(&self.cfg) <= self.cfg.sleep_threshold)
-            .map(|s| s.id);
-
-        // Early return (most likely case)
-        let shutdown_station_id = match shutdown_station_id {
-            Some(id) => id,
-            None => return,
+    /// Takes single user and assigns it to station chosen by redirect policy
+    fn redirect(
+        &self,
+        user: User,
+        from: usize,
+        sim_state: &SimState,
+        policy: &mut dyn RedirectPolicy,
+        rng: &mut StdRng,
+        stations: &mut [BaseStation],
+    ) -> Result<usize, User> {
+        let idx = match policy.select(from, &user, stations, &self.cfg, rng) {
+            Some(idx) => idx,
+            None => return Err(user),
         };
+        let redirect_station = &mut stations[idx];
+        redirect_station.update_counters(sim_state.time);
+        redirect_station.redirect_here(user)?;
+        Ok(redirect_station.id)
+    }
 
-        // Get all active stations (except shutdown candidate) id and their remaining capacity
-        let mut active_capacity: usize = 0;
-        let active_capacity_list: Vec<(usize, usize)> = stations
-            .iter()
-            .filter(|s| s.is_active() && s.id != shutdown_station_id)
-            .map(|s| {
-                let capacity = self.cfg.resources_count - s.get_usage_raw();
-                active_capacity += capacity;
-                (s.id, capacity)
-            })
-            .collect();
+    /// Applies decisions of sleep controller.
+    /// Returns indexes of all stations modified in the process
+    fn power_up_down(
+        &self,
+        sim_state: &SimState,
+        controller: &mut dyn SleepController,
+        stations: &mut [BaseStation],
+    ) -> Vec<usize> {
+        let mut modified = Vec::new();
+        let mut decisions = controller.decide(sim_state, stations, &self.cfg);
+        decisions.extend(controller.demote(sim_state, stations));
+        for decision in decisions {
+            match decision {
+                PowerDecision::WakeUp {
+                    station,
+                    offload_from,
+                } => modified.extend(self.wake_up(sim_state, station, offload_from, stations)),
+                PowerDecision::ShutDown { station } => {
+                    modified.extend(self.shut_down(sim_state, station, stations))
+                }
+                PowerDecision::Demote { station } => {
+                    // station could be woken up by decision of the same round
+                    if matches!(stations[station].state, BaseStationState::Sleep(_)) {
+                        stations[station].update_counters(sim_state.time);
+                        stations[station].demote(sim_state.time);
+                        modified.push(station);
+                    }
+                }
+            }
+        }
+        modified
+    }
 
-        // There always must be at least single active station,
-        // so when looking for station for power down there must be
-        // at least 2 active stations
-        if active_capacity_list.len() < 2 {
-            return;
+    fn wake_up(
+        &self,
+        sim_state: &SimState,
+        idx: usize,
+        offload_from: Option<usize>,
+        stations: &mut [BaseStation],
+    ) -> Vec<usize> {
+        if !matches!(stations[idx].state, BaseStationState::Sleep(_)) {
+            return Vec::new();
         }
+        stations[idx].update_counters(sim_state.time);
+        let delay = stations[idx].wakeup_delay();
+        let (mut users, uncovered): (Vec<User>, Vec<User>) = match offload_from {
+            Some(heavy_load_idx) => {
+                // Redirect half of load to woken up station
+                stations[heavy_load_idx].update_counters(sim_state.time);
+                stations[heavy_load_idx]
+                    .release_half()
+                    .into_iter()
+                    .partition(|user| covers_user(user, idx, &self.cfg))
+            }
+            None => (Vec::new(), Vec::new()),
+        };
+        for user in users.iter_mut() {
+            user.end += delay;
+        }
+        stations[idx].power_up(sim_state.time);
+        stations[idx].redirect_here_vec(&mut users, usize::MAX);
+        if let Some(heavy_load_idx) = offload_from {
+            // users requesting more blocks than woken up station has
+            // and users outside of its coverage stay where they were
+            for user in users.iter_mut() {
+                user.end -= delay;
+            }
+            for user in users.into_iter().chain(uncovered) {
+                let res = stations[heavy_load_idx].redirect_here(user);
+                debug_assert!(res.is_ok());
+            }
+        }
+        let mut modified = vec![idx];
+        modified.extend(offload_from);
+        modified
+    }
 
-        // Check if there is enough capacity in active stations for users from shutdown station
-        let station = &mut stations[shutdown_station_id];
-        if station.get_usage_raw() >= active_capacity {
-            return;
+    fn shut_down(
+        &self,
+        sim_state: &SimState,
+        shutdown_idx: usize,
+        stations: &mut [BaseStation],
+    ) -> Vec<usize> {
+        if !stations[shutdown_idx].is_active() {
+            return Vec::new();
+        }
+        // Get all active stations (except shutdown candidate) id and their remaining capacity
+        let active_capacity_list = match offload_targets(shutdown_idx, stations, &self.cfg) {
+            Some(list) => list,
+            None => return Vec::new(),
+        };
+        // Users are redirected in order of their end time. Users with multiple blocks may not fit
+        // into remaining space of other stations and users in spatial mode may not be covered
+        // by any of them, shutdown is then cancelled before any user is moved
+        let mut users: Vec<&User> = stations[shutdown_idx].users().collect();
+        users.sort_by_key(|u| (u.end, u.blocks));
+        let blocks: Vec<usize> = users.iter().map(|u| u.blocks).collect();
+        let covers = |user: usize, idx: usize| covers_user(users[user], idx, &self.cfg);
+        let plan = match plan_offload(&blocks, &active_capacity_list, covers) {
+            Some(plan) => plan,
+            None => return Vec::new(),
+        };
+        for (idx, _) in active_capacity_list.iter() {
+            stations[*idx].update_counters(sim_state.time);
         }
+        let station = &mut stations[shutdown_idx];
+        station.update_counters(sim_state.time);
 
         let mut users = station.release_all();
-        station.state = BaseStationState::PowerDown(sim_state.time + self.cfg.wakeup_delay * 1000);
-
-        // Redirect users to all other active stations, proportionally to their remaining capacity
-        let u_len = users.len();
-        for (idx, capacity) in &active_capacity_list[0..active_capacity_list.len() - 1] {
-            let count = capacity * u_len / active_capacity;
-            stations[*idx].redirect_here_vec(&self.cfg, &mut users, count);
+        users.sort_by_key(|u| (u.end, u.blocks));
+        station.power_down(sim_state.time);
+        for (user, idx) in users.into_iter().zip(plan) {
+            let res = stations[idx].redirect_here(user);
+            debug_assert!(res.is_ok());
         }
-        // Redirect all remaining users to last station
-        let (last_idx, _) = active_capacity_list[active_capacity_list.len() - 1];
-        let u_len = users.len();
-        stations[last_idx].redirect_here_vec(&self.cfg, &mut users, u_len);
-        debug_assert_eq!(users.len(), 0);
+        let mut modified: Vec<usize> = active_capacity_list.iter().map(|(id, _)| *id).collect();
+        modified.push(shutdown_idx);
+        modified
     }
 
-    pub fn run(&self, run_no: usize) -> SimResults {
-        let mut sim_res = SimResults::new_zero(&self.cfg);
-        let path = PathBuf::from(format!("sim.run_{}_no_", run_no));
-        let partial_sim_res: Vec<SimResults> = (0..self.cli.iterations)
+    pub fn run(&self, run_no: usize) -> Result<SimResults, CassidyError> {
+        let warmup = if self.cfg.auto_warmup {
+            self.detect_warmup()?
+        } else {
+            (self.cfg.warmup * 3600.0 * 1_000_000.0) as u64
+        };
+        let path = self.options.log_dir.join(format!("sim.run_{}_no_", run_no));
+        let partial_sim_res: Vec<SimResults> = (0..self.options.iterations)
             .into_par_iter()
             .map(|i| {
                 let mut log_path: OsString = path.clone().into();
                 log_path.push(i.to_string());
-                let res = self.simulate(i, log_path.into());
-                if self.cli.show_partial_results {
+                let res = self.simulate_with_warmup(i, log_path.into(), warmup, None)?;
+                if self.options.show_partial_results {
                     println!("Partial result - iteration: {}", i);
                     println!("{}", res.get_report());
                 }
-                res
+                Ok(res)
             })
-            .collect();
+            .collect::<Result<_, CassidyError>>()?;
         // write partial results to separate log file
-        if self.cli.show_partial_results {
-            let partial_path = format!("sim_partial.run_{}", run_no);
-            Self::save_partial_results(&partial_sim_res, &partial_path.into());
+        if self.options.show_partial_results {
+            let partial_path = self
+                .options
+                .log_dir
+                .join(format!("sim_partial.run_{}", run_no));
+            Self::save_partial_results(&partial_sim_res, &partial_path)?;
         }
         // average results
-        for partial in partial_sim_res.iter() {
-            sim_res.add(partial);
-        }
-        sim_res.div(self.cli.iterations as f64);
-        sim_res
-    }
-
-    fn save_partial_results(results: &Vec<SimResults>, path: &PathBuf) {
-        let file = std::fs::File::create(path);
-        match file {
-            Ok(mut f) => {
-                let _ = f.write_all(results[0].get_csv_header().as_bytes());
-                let _ = f.write_all("\n".as_bytes());
-                for res in results.iter() {
-                    let _ = f.write_all(res.get_csv().as_bytes());
-                    let _ = f.write_all("\n".as_bytes());
-                }
-            }
-            Err(e) => println!("{}", e),
+        let mut res =
+            SimResults::aggregate(&self.cfg, &partial_sim_res, self.options.confidence_level);
+        res.detected_warmup = self
+            .cfg
+            .auto_warmup
+            .then_some(warmup as f64 / 3600.0 / 1_000_000.0);
+        Ok(res)
+    }
+
+    fn save_partial_results(results: &[SimResults], path: &PathBuf) -> Result<(), CassidyError> {
+        let mut data = results[0].get_csv_header() + "\n";
+        for res in results.iter() {
+            data += &res.get_csv();
+            data += "\n";
         }
+        std::fs::write(path, data).map_err(|source| CassidyError::Io {
+            path: path.clone(),
+            source,
+        })
     }
 }
 
@@ -424,27 +1077,16 @@ impl SimContainer {
 impl SimContainer {
     #[allow(dead_code)]
     pub fn new_test(s: usize, r: usize) -> SimContainer {
-        let cli = Cli {
-            with_config: None,
-            seed: Some(1),
-            log: false,
-            log_wave: false,
-            duration: 1.0,
-            iterations: 1,
-            enable_sleep: false,
-            save_default_config: None,
-            show_partial_results: false,
-            samples: 1,
-            walk_over: None,
+        let cfg = Config {
+            stations_count: s,
+            resources_count: r,
+            ..Default::default()
         };
-        let mut cfg = cli.create_config().unwrap();
-        // convert lambda timestamps from hours to microseconds
-        for p in cfg.lambda_coefs.iter_mut() {
-            p.time *= 3600.0 * 1000_000.0;
-        }
-        cfg.stations_count = s;
-        cfg.resources_count = r;
-        SimContainer { cli, cfg }
+        SimulationBuilder::new(cfg)
+            .duration(1.0)
+            .seed(1)
+            .build()
+            .unwrap()
     }
 }
 
@@ -454,8 +1096,17 @@ mod test {
 
     use crate::{
         basestation::{BaseStation, BaseStationEvent, BaseStationState},
+        config::{
+            AdmissionControl, BlocksDistribution, Config, LambdaPoint, MobilityModel, Position,
+            PriorityConfig, QueueConfig, ServiceClass, ServiceTime, SleepControllerKind,
+            SleepLevel, SpatialConfig, Topology, UserPlacement,
+        },
         logger::Logger,
-        sim_container::{SimContainer, SimState},
+        redirect::create_policy,
+        sim_container::{
+            AccountResult, SchedulerKind, SimContainer, SimResults, SimState, SimulationBuilder,
+        },
+        sleep::create_controller,
         user::User,
     };
     use std::{io::Write, path::PathBuf, process::Command};
@@ -464,6 +1115,7 @@ mod test {
     fn redirect() {
         // test user redirection
         let container = SimContainer::new_test(3, 2);
+        let mut policy = create_policy(container.cfg.redirect_policy);
         let mut sim_state = SimState::new(&container.cfg);
         let mut stations: Vec<BaseStation> = Vec::new();
         let mut rng = StdRng::seed_from_u64(1);
@@ -472,85 +1124,107 @@ mod test {
         for i in 0..container.cfg.stations_count {
             stations.push(BaseStation::new(i, &container.cfg, 1.0, &mut rng));
         }
-        stations[0].execute_event(
-            &BaseStationEvent::AddUser,
-            &container.cfg,
-            &mut sim_state,
-            &mut rng,
-            false,
-            &mut logger,
-        );
-        stations[0].execute_event(
-            &BaseStationEvent::AddUser,
-            &container.cfg,
-            &mut sim_state,
-            &mut rng,
-            false,
-            &mut logger,
-        );
-        stations[2].execute_event(
-            &BaseStationEvent::AddUser,
-            &container.cfg,
-            &mut sim_state,
+        stations[0]
+            .execute_event(
+                &BaseStationEvent::AddUser,
+                &container.cfg,
+                &mut sim_state,
+                &mut rng,
+                false,
+                &mut logger,
+            )
+            .unwrap();
+        stations[0]
+            .execute_event(
+                &BaseStationEvent::AddUser,
+                &container.cfg,
+                &mut sim_state,
+                &mut rng,
+                false,
+                &mut logger,
+            )
+            .unwrap();
+        stations[2]
+            .execute_event(
+                &BaseStationEvent::AddUser,
+                &container.cfg,
+                &mut sim_state,
+                &mut rng,
+                false,
+                &mut logger,
+            )
+            .unwrap();
+        // 2 redirection candidates with different usage
+        let user = User::test(1, 10);
+        let res = container.redirect(
+            user,
+            0,
+            &sim_state,
+            policy.as_mut(),
             &mut rng,
-            false,
-            &mut logger,
+            &mut stations,
         );
-        // 2 redirection candidates with different usage
-        let user = User {
-            id: 1,
-            start: 0,
-            end: 10,
-        };
-        let res = container.redirect(user, &mut stations);
-        assert_eq!(res.is_ok(), true);
+        assert!(res.is_ok());
         assert_eq!(res.unwrap(), 1);
         // 2 redirection candidates with same usage
-        let user = User {
-            id: 2,
-            start: 0,
-            end: 10,
-        };
-        let res = container.redirect(user, &mut stations);
-        assert_eq!(res.is_ok(), true);
+        let user = User::test(2, 10);
+        let res = container.redirect(
+            user,
+            0,
+            &sim_state,
+            policy.as_mut(),
+            &mut rng,
+            &mut stations,
+        );
+        assert!(res.is_ok());
         assert_eq!(res.unwrap(), 1);
         // single redirection candidate
-        let user = User {
-            id: 3,
-            start: 0,
-            end: 10,
-        };
-        let res = container.redirect(user, &mut stations);
-        assert_eq!(res.is_ok(), true);
-        assert_eq!(res.unwrap(), 2);
-        stations[2].execute_event(
-            &BaseStationEvent::AddUser,
-            &container.cfg,
-            &mut sim_state,
+        let user = User::test(3, 10);
+        let res = container.redirect(
+            user,
+            0,
+            &sim_state,
+            policy.as_mut(),
             &mut rng,
-            false,
-            &mut logger,
+            &mut stations,
         );
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), 2);
+        stations[2]
+            .execute_event(
+                &BaseStationEvent::AddUser,
+                &container.cfg,
+                &mut sim_state,
+                &mut rng,
+                false,
+                &mut logger,
+            )
+            .unwrap();
         // no redirection candidates
-        let user = User {
-            id: 3,
-            start: 0,
-            end: 10,
-        };
-        let res = container.redirect(user, &mut stations);
-        assert_eq!(res.is_err(), true);
+        let user = User::test(3, 10);
+        let res = container.redirect(
+            user,
+            0,
+            &sim_state,
+            policy.as_mut(),
+            &mut rng,
+            &mut stations,
+        );
+        assert!(res.is_err());
     }
 
     #[test]
     fn single_sim() {
         // test single simulation run
         let mut container = SimContainer::new_test(3, 10);
-        container.cli.duration = 1.0 / 3600.0 * 60.0;
-        container.cli.log = true;
-        let res = container.simulate(0, PathBuf::from("tests/single_sim.log"));
+        container.options.duration = 1.0 / 3600.0 * 60.0;
+        container.options.log = true;
+        let res = container
+            .simulate(0, PathBuf::from("tests/single_sim.log"))
+            .unwrap();
         let mut file =
             std::fs::File::create("tests/single_sim.report").expect("Couldn't create report file.");
-        file.write(res.get_report().as_bytes())
+        file.write_all(res.get_report().as_bytes())
             .expect("Couldn;t write report to file");
         let diff = Command::new("diff")
             .args(["tests/single_sim.log", "tests/references/single_sim.log"])
@@ -571,9 +1245,606 @@ mod test {
         assert_eq!(diff.status.code().unwrap(), 0);
     }
 
+    #[test]
+    fn scheduler_results() {
+        // both schedulers must produce identical simulation
+        let mut results = Vec::new();
+        for kind in [SchedulerKind::Scan, SchedulerKind::Queue] {
+            let mut container = SimContainer::new_test(10, 10);
+            container.options.duration = 0.5;
+            container.options.enable_sleep = true;
+            container.options.scheduler = kind;
+            container.cfg.lambda = 5.0;
+            let res = container
+                .simulate(0, PathBuf::from("tests/scheduler.log"))
+                .unwrap();
+            results.push(res.get_csv());
+        }
+        assert_eq!(results[0], results[1]);
+    }
+
+    #[test]
+    fn lambda_events() {
+        let cfg = Config {
+            stations_count: 3,
+            resources_count: 10,
+            lambda: 1.0,
+            lambda_coefs: vec![
+                LambdaPoint {
+                    time: 0.001,
+                    coef: 1.0,
+                },
+                LambdaPoint {
+                    time: 0.002,
+                    coef: 2.0,
+                },
+            ],
+            ..Default::default()
+        };
+        let container = SimulationBuilder::new(cfg)
+            .duration(0.01)
+            .seed(1)
+            .log(true)
+            .build()
+            .unwrap();
+        container
+            .simulate(0, PathBuf::from("tests/lambda_events.log"))
+            .unwrap();
+        // lambda changes exactly at boundaries of profile phases, which last 0.001 h and 0.002 h
+        let log = std::fs::read_to_string("tests/lambda_events.log").unwrap();
+        let updates: Vec<(u64, f64)> = log
+            .lines()
+            .filter(|line| line.contains("Lambda updated"))
+            .map(|line| {
+                let (time, msg) = line.split_once('\t').unwrap();
+                let lambda = msg.rsplit(' ').next().unwrap();
+                (time.parse().unwrap(), lambda.parse().unwrap())
+            })
+            .collect();
+        let expected: Vec<(u64, f64)> = [3.6_f64, 10.8, 14.4, 21.6, 25.2, 32.4, 36.0]
+            .iter()
+            .zip([2.0, 1.0].iter().cycle())
+            .map(|(time, lambda)| ((time * 1_000_000.0).round() as u64, *lambda))
+            .collect();
+        assert_eq!(updates, expected);
+    }
+
+    #[test]
+    fn zero_lambda() {
+        // no users arrive during the first phase, but arrivals start again in the second one
+        let position = |x: f64, y: f64| Position { x, y };
+        let mut cfg = Config {
+            stations_count: 2,
+            resources_count: 100,
+            lambda: 10.0,
+            lambda_coefs: vec![
+                LambdaPoint {
+                    time: 0.01,
+                    coef: 0.0,
+                },
+                LambdaPoint {
+                    time: 0.01,
+                    coef: 1.0,
+                },
+            ],
+            topology: Topology::Coverage {
+                positions: vec![position(0.0, 50.0), position(100.0, 50.0)],
+                radius: 100.0,
+                neighbours: Vec::new(),
+            },
+            ..Default::default()
+        };
+        for spatial in [false, true] {
+            if spatial {
+                cfg.spatial = Some(SpatialConfig {
+                    width: 100.0,
+                    height: 100.0,
+                    ..Default::default()
+                });
+            }
+            let container = SimulationBuilder::new(cfg.clone())
+                .duration(0.02)
+                .seed(1)
+                .build()
+                .unwrap();
+            let res = container
+                .simulate(0, PathBuf::from("tests/zero_lambda.log"))
+                .unwrap();
+            // 2 stations with lambda 10 for 36 s
+            assert!(
+                (600..840).contains(&res.total_users),
+                "spatial: {} users: {}",
+                spatial,
+                res.total_users
+            );
+        }
+    }
+
+    #[test]
+    fn warmup() {
+        let mut container = SimContainer::new_test(3, 10);
+        container.options.duration = 0.1;
+        let full = container
+            .simulate(0, PathBuf::from("tests/warmup.log"))
+            .unwrap();
+        container.cfg.warmup = 0.05;
+        let res = container
+            .simulate(0, PathBuf::from("tests/warmup.log"))
+            .unwrap();
+        // same events are simulated, but only second half is recorded
+        assert!(res.total_users > 0 && res.total_users < full.total_users);
+        assert!(res.dropped_users <= full.dropped_users);
+        for station in res.stations.iter() {
+            assert_eq!(station.average_power, container.cfg.active_power);
+        }
+
+        let warmup = container.detect_warmup().unwrap();
+        assert!(warmup <= 180_000_000, "detected warm-up: {}", warmup);
+        // detected period is returned with results instead of being printed
+        assert_eq!(container.run(0).unwrap().detected_warmup, None);
+        container.cfg.auto_warmup = true;
+        let res = container.run(0).unwrap();
+        assert_eq!(
+            res.detected_warmup,
+            Some(warmup as f64 / 3600.0 / 1_000_000.0)
+        );
+    }
+
+    #[test]
+    fn service_classes() {
+        let mut container = SimContainer::new_test(3, 20);
+        container.options.duration = 0.1;
+        container.cfg.lambda = 20.0;
+        container.cfg.service_classes = vec![
+            ServiceClass {
+                name: "voice".to_owned(),
+                share: 3.0,
+                process_time_min: None,
+                process_time_max: None,
+                service_time: None,
+                blocks: BlocksDistribution::Constant { count: 1 },
+                priority: 0,
+            },
+            ServiceClass {
+                name: "video".to_owned(),
+                share: 1.0,
+                process_time_min: None,
+                process_time_max: None,
+                service_time: None,
+                blocks: BlocksDistribution::Constant { count: 8 },
+                priority: 0,
+            },
+            ServiceClass {
+                name: "rare".to_owned(),
+                share: 1e-9,
+                process_time_min: None,
+                process_time_max: None,
+                service_time: None,
+                blocks: BlocksDistribution::Constant { count: 1 },
+                priority: 0,
+            },
+        ];
+        let res = container
+            .simulate(0, PathBuf::from("tests/service_classes.log"))
+            .unwrap();
+        assert_eq!(res.classes.len(), 3);
+        let users: usize = res.classes.iter().map(|c| c.total_users).sum();
+        let dropped: usize = res.classes.iter().map(|c| c.dropped_users).sum();
+        assert_eq!(users, res.total_users);
+        assert_eq!(dropped, res.dropped_users);
+        // wide users are blocked more often
+        assert!(res.classes[1].blocking_rate > res.classes[0].blocking_rate);
+        assert!(res.get_csv_header().contains("video_blocking_rate"));
+        // class without arrivals has no blocked users
+        assert_eq!(res.classes[2].total_users, 0);
+        assert_eq!(res.classes[2].blocking_rate, 0.0);
+    }
+
+    #[test]
+    fn trace() {
+        let cfg = Config {
+            stations_count: 3,
+            resources_count: 2,
+            trace: Some(PathBuf::from("tests/references/trace.csv")),
+            ..Default::default()
+        };
+        let container = SimulationBuilder::new(cfg)
+            .duration(0.01)
+            .seed(1)
+            .build()
+            .unwrap();
+        let res = container
+            .simulate(0, PathBuf::from("tests/trace.log"))
+            .unwrap();
+        // user requesting 3 blocks does not fit into any station
+        assert_eq!(res.total_users, 3);
+        assert_eq!(res.dropped_users, 1);
+        // trace is exhausted after 1 s, so it is replayed in each iteration
+        let res = container
+            .simulate(1, PathBuf::from("tests/trace.log"))
+            .unwrap();
+        assert_eq!(res.total_users, 3);
+        assert_eq!(res.neighbour_blocked_users, None);
+    }
+
+    #[test]
+    fn topology() {
+        let cfg = Config {
+            stations_count: 3,
+            resources_count: 2,
+            trace: Some(PathBuf::from("tests/references/trace.csv")),
+            topology: Topology::Adjacency {
+                neighbours: vec![vec![], vec![2], vec![]],
+            },
+            ..Default::default()
+        };
+        let container = SimulationBuilder::new(cfg)
+            .duration(0.01)
+            .seed(1)
+            .build()
+            .unwrap();
+        let res = container
+            .simulate(0, PathBuf::from("tests/topology.log"))
+            .unwrap();
+        // isolated station 0 has no neighbour for user requesting 3 blocks
+        assert_eq!(res.dropped_users, 1);
+        assert_eq!(res.neighbour_blocked_users, Some(1));
+        assert!(res
+            .get_report()
+            .contains("users dropped without free neighbour: 1"));
+    }
+
+    #[test]
+    fn spatial() {
+        // stations cover left and right edge of area, middle part is a coverage hole
+        let position = |x: f64, y: f64| Position { x, y };
+        let cfg = Config {
+            stations_count: 2,
+            resources_count: 100,
+            lambda: 10.0,
+            topology: Topology::Coverage {
+                positions: vec![position(0.0, 50.0), position(1000.0, 50.0)],
+                radius: 250.0,
+                neighbours: Vec::new(),
+            },
+            spatial: Some(SpatialConfig {
+                width: 1000.0,
+                height: 100.0,
+                placement: UserPlacement::Uniform,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let container = SimulationBuilder::new(cfg)
+            .duration(0.1)
+            .seed(1)
+            .build()
+            .unwrap();
+        let res = container
+            .simulate(0, PathBuf::from("tests/spatial.log"))
+            .unwrap();
+        // 2 stations with lambda 10 and coefficient 0.5 for 360 s
+        assert!(
+            (3400..3800).contains(&res.total_users),
+            "users: {}",
+            res.total_users
+        );
+        let uncovered = res.uncovered_users.unwrap() as f64 / res.total_users as f64;
+        assert!((uncovered - 0.5).abs() < 0.05, "uncovered: {}", uncovered);
+        assert_eq!(res.dropped_users, res.uncovered_users.unwrap());
+        assert_eq!(res.dropped_calls, None);
+        assert!(res.stations.iter().all(|s| s.average_usage > 0.0));
+    }
+
+    #[test]
+    fn mobility() {
+        // whole area is covered and users move between two overloaded stations
+        let position = |x: f64, y: f64| Position { x, y };
+        let cfg = Config {
+            stations_count: 2,
+            resources_count: 5,
+            lambda: 2.0,
+            topology: Topology::Coverage {
+                positions: vec![position(250.0, 250.0), position(750.0, 250.0)],
+                radius: 400.0,
+                neighbours: Vec::new(),
+            },
+            spatial: Some(SpatialConfig {
+                width: 1000.0,
+                height: 500.0,
+                mobility: MobilityModel::RandomWaypoint {
+                    speed_min: 20.0,
+                    speed_max: 30.0,
+                    pause: 0,
+                },
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let container = SimulationBuilder::new(cfg)
+            .duration(0.05)
+            .seed(1)
+            .build()
+            .unwrap();
+        let res = container
+            .simulate(0, PathBuf::from("tests/mobility.log"))
+            .unwrap();
+        assert_eq!(res.uncovered_users, Some(0));
+        assert!(res.handovers.unwrap() > 0);
+        assert!(res.dropped_calls.unwrap() > 0);
+        // dropped calls are not counted as blocked arrivals
+        assert!(res.dropped_users < res.total_users);
+        assert!(res.get_report().contains("- dropped calls: "));
+    }
+
+    #[test]
+    fn queue() {
+        let cfg = Config {
+            stations_count: 2,
+            resources_count: 5,
+            lambda: 2.0,
+            ..Default::default()
+        };
+        let simulate = |queue: Option<QueueConfig>| {
+            let cfg = Config {
+                queue,
+                ..cfg.clone()
+            };
+            let container = SimulationBuilder::new(cfg)
+                .duration(0.1)
+                .seed(1)
+                .build()
+                .unwrap();
+            container
+                .simulate(0, PathBuf::from("tests/queue.log"))
+                .unwrap()
+        };
+        let no_queue = simulate(None);
+        assert!(no_queue.queue.is_none());
+        let res = simulate(Some(QueueConfig {
+            max_length: 10,
+            patience: Some(ServiceTime::Exponential { mean: 5000.0 }),
+            ..Default::default()
+        }));
+        let queue = res.queue.as_ref().unwrap();
+        // users waiting in queue are not dropped, unless queue is full
+        assert!(res.dropped_users < no_queue.dropped_users);
+        assert!(queue.queued_users > 0 && queue.abandoned_users > 0);
+        assert!(queue.abandoned_users < queue.queued_users);
+        assert!(queue.average_wait > 0.0, "{:?}", queue);
+        assert!(queue.average_queue_length > 0.0 && queue.average_queue_length <= 10.0);
+        assert!(res.get_report().contains("- abandonment rate: "));
+
+        // nobody waits under light load
+        let cfg = Config {
+            lambda: 0.1,
+            resources_count: 100,
+            queue: Some(QueueConfig::default()),
+            ..cfg
+        };
+        let container = SimulationBuilder::new(cfg)
+            .duration(0.01)
+            .iterations(2)
+            .seed(1)
+            .build()
+            .unwrap();
+        let res = container.run(0).unwrap();
+        let queue = res.queue.as_ref().unwrap();
+        assert_eq!(queue.queued_users, 0);
+        assert_eq!((queue.abandonment_rate, queue.average_wait), (0.0, 0.0));
+    }
+
+    #[test]
+    fn priorities() {
+        let cfg = Config {
+            stations_count: 2,
+            resources_count: 5,
+            lambda: 2.0,
+            ..Default::default()
+        };
+        let simulate = |preemption: bool| {
+            let cfg = Config {
+                priorities: Some(PriorityConfig {
+                    shares: vec![3.0, 1.0],
+                    preemption,
+                }),
+                ..cfg.clone()
+            };
+            let container = SimulationBuilder::new(cfg)
+                .duration(0.1)
+                .seed(1)
+                .build()
+                .unwrap();
+            container
+                .simulate(0, PathBuf::from("tests/priorities.log"))
+                .unwrap()
+        };
+        let no_preemption = simulate(false);
+        assert!(no_preemption
+            .priorities
+            .iter()
+            .all(|p| p.preempted_users == 0));
+        let res = simulate(true);
+        let (low, high) = (&res.priorities[0], &res.priorities[1]);
+        assert_eq!(low.total_users + high.total_users, res.total_users);
+        assert_eq!(low.dropped_users + high.dropped_users, res.dropped_users);
+        // high priority users are blocked only by each other, low priority users take their place
+        assert_eq!(high.preempted_users, 0);
+        assert!(low.preempted_users > 0);
+        assert!(high.drop_rate < no_preemption.priorities[1].drop_rate);
+        assert!(low.drop_rate > no_preemption.priorities[0].drop_rate);
+        // without queue every arrival is either admitted or dropped,
+        // preempted users, which could not be redirected, are counted separately
+        assert_eq!(high.admitted_users + high.dropped_users, high.total_users);
+        assert_eq!(low.admitted_users + low.dropped_users, low.total_users);
+        assert!(res.preempted_dropped.unwrap() <= low.preempted_users);
+        assert!(no_preemption.preempted_dropped.is_none());
+        assert!(res.get_report().contains("Priority levels results:"));
+    }
+
+    #[test]
+    fn sleep_levels() {
+        let level = |name: &str, power, wakeup_delay, idle_time| SleepLevel {
+            name: name.to_owned(),
+            power,
+            wakeup_delay,
+            idle_time,
+        };
+        let cfg = Config {
+            stations_count: 5,
+            resources_count: 10,
+            lambda: 0.5,
+            sleep_levels: vec![
+                level("light", 50.0, 10, 0),
+                level("deep", 10.0, 1000, 60_000),
+            ],
+            ..Default::default()
+        };
+        let container = SimulationBuilder::new(cfg)
+            .duration(1.0)
+            .seed(1)
+            .enable_sleep(true)
+            .build()
+            .unwrap();
+        let res = container
+            .simulate(0, PathBuf::from("tests/sleep_levels.log"))
+            .unwrap();
+        assert_eq!(res.sleep_levels, vec!["light", "deep"]);
+        for station in res.stations.iter() {
+            let total: f64 = station.sleep_level_times.iter().sum();
+            assert!((total - station.average_sleep_time).abs() < 1e-9);
+        }
+        // stations initially put to sleep are demoted after idle time of deep level
+        let deep: f64 = res.stations.iter().map(|s| s.sleep_level_times[1]).sum();
+        assert!(deep > 0.0);
+        assert!(res.get_report().contains("Time spent in sleep levels [%]:"));
+        assert!(res
+            .metrics()
+            .iter()
+            .any(|(name, _)| name == "station1_deep_time"));
+    }
+
+    #[test]
+    fn admission() {
+        let simulate = |admission: AdmissionControl| {
+            let cfg = Config {
+                stations_count: 2,
+                resources_count: 10,
+                lambda: 1.0,
+                admission,
+                ..Default::default()
+            };
+            let container = SimulationBuilder::new(cfg)
+                .duration(0.1)
+                .seed(1)
+                .build()
+                .unwrap();
+            container
+                .simulate(0, PathBuf::from("tests/admission.log"))
+                .unwrap()
+        };
+        let greedy = simulate(AdmissionControl::Greedy);
+        assert_eq!(greedy.admission_blocked, None);
+        let guard = simulate(AdmissionControl::GuardChannel { reserved: 3 });
+        let blocked = guard.admission_blocked.unwrap();
+        assert!(blocked > 0);
+        // refused arrivals are blocked sooner, but some of them are redirected
+        assert!(guard.dropped_users > greedy.dropped_users);
+        assert!(guard.dropped_users < greedy.dropped_users + blocked);
+        assert!(guard
+            .get_report()
+            .contains("- users refused by admission control: "));
+    }
+
+    #[test]
+    fn energy_accounting() {
+        let cfg = Config {
+            stations_count: 5,
+            resources_count: 10,
+            lambda: 0.5,
+            tariff: Some(vec![0.25]),
+            carbon_intensity: Some((0..24).map(|h| 200.0 + h as f64 * 10.0).collect()),
+            ..Default::default()
+        };
+        let container = SimulationBuilder::new(cfg)
+            .duration(2.0)
+            .seed(1)
+            .enable_sleep(true)
+            .build()
+            .unwrap();
+        let res = container
+            .simulate(0, PathBuf::from("tests/energy_accounting.log"))
+            .unwrap();
+        let cost = res.cost.as_ref().unwrap();
+        // constant tariff prices total energy of all stations
+        assert!((cost.total - res.total_energy * 0.25).abs() < 1e-9);
+        assert!((cost.stations.iter().sum::<f64>() - cost.total).abs() < 1e-9);
+        // sleeping stations consume less than always-on baseline
+        assert!(cost.savings > 0.0);
+        assert!(cost.total < cost.baseline);
+        let emissions = res.emissions.as_ref().unwrap();
+        assert!(emissions.savings > 0.0);
+        assert!((emissions.stations.iter().sum::<f64>() - emissions.total).abs() < 1e-9);
+        let report = res.get_report();
+        assert!(report.contains("- total energy cost: "));
+        assert!(report.contains("Stations cost and emissions:"));
+        assert!(res
+            .metrics()
+            .iter()
+            .any(|(name, _)| name == "station4_emissions"));
+    }
+
+    #[test]
+    fn aggregate_results() {
+        let container = SimContainer::new_test(2, 10);
+        let partials: Vec<SimResults> = [(10, 0.2), (20, 0.4)]
+            .into_iter()
+            .map(|(users, usage)| SimResults {
+                total_users: users,
+                average_usage: usage,
+                ..SimResults::new_zero(&container.cfg)
+            })
+            .collect();
+        let res = SimResults::aggregate(&container.cfg, &partials, 0.95);
+        assert_eq!(res.total_users, 15);
+        let spread = res.spread.as_ref().unwrap();
+        assert_eq!(spread.iterations, 2);
+        assert_eq!(spread.metrics.len(), res.metrics().len());
+        // processed_users: mean 15, std dev 7.07, t(0.975, 1) = 12.706
+        let users = spread.metrics[0];
+        assert_eq!((users.min, users.max), (10.0, 20.0));
+        assert!((users.std_dev - 50.0_f64.sqrt()).abs() < 1e-9);
+        assert!((users.ci_high - 15.0 - 12.7062 * 5.0).abs() < 1e-2);
+        assert_eq!(
+            res.get_csv_header().split(',').count(),
+            res.get_csv().split(',').count()
+        );
+        // single iteration has no spread
+        let res = SimResults::aggregate(&container.cfg, &partials[..1], 0.95);
+        assert!(res.spread.is_none());
+
+        // savings are calculated from averaged cost, not averaged across iterations
+        let cfg = Config {
+            tariff: Some(vec![1.0]),
+            ..container.cfg.clone()
+        };
+        let partials: Vec<SimResults> = [(50.0, 100.0), (18.0, 20.0)]
+            .into_iter()
+            .map(|(total, baseline)| SimResults {
+                cost: Some(AccountResult::new(total, vec![total / 2.0; 2], baseline)),
+                ..SimResults::new_zero(&cfg)
+            })
+            .collect();
+        assert!((partials[1].cost.as_ref().unwrap().savings - 10.0).abs() < 1e-9);
+        let cost = SimResults::aggregate(&cfg, &partials, 0.95).cost.unwrap();
+        assert_eq!((cost.total, cost.baseline), (34.0, 60.0));
+        assert!((cost.savings - (1.0 - 34.0 / 60.0) * 100.0).abs() < 1e-9);
+    }
+
     #[test]
     fn try_wakeup() {
         let sim = SimContainer::new_test(3, 10);
+        let mut controller = create_controller(&SleepControllerKind::Threshold, &sim.cfg);
         let sim_state = SimState::new(&sim.cfg);
         let mut rng = StdRng::seed_from_u64(1);
         let mut stations = vec![
@@ -581,36 +1852,32 @@ mod test {
             BaseStation::new(1, &sim.cfg, 1.0, &mut rng),
             BaseStation::new(1, &sim.cfg, 1.0, &mut rng),
         ];
-        stations[1].state = BaseStationState::Sleep;
+        stations[1].state = BaseStationState::Sleep(0);
         for i in 0..10 {
-            stations[0].force_add_user(User {
-                id: i,
-                start: 0,
-                end: i as u64,
-            });
+            stations[0].force_add_user(User::test(i, i as u64));
         }
 
-        sim.try_wakeup(&sim_state, 0, &mut stations);
+        sim.power_up_down(&sim_state, controller.as_mut(), &mut stations);
         // assert stations state
         assert!(std::matches!(
             stations[1].state,
             BaseStationState::PowerUp(_)
         ));
         // assert redirected users
-        assert_eq!(stations[0].get_usage(&sim.cfg), 50.0);
-        assert_eq!(stations[1].get_usage(&sim.cfg), 50.0);
-        assert_eq!(stations[2].get_usage(&sim.cfg), 0.0);
+        assert_eq!(stations[0].get_usage(), 50.0);
+        assert_eq!(stations[1].get_usage(), 50.0);
+        assert_eq!(stations[2].get_usage(), 0.0);
 
-        sim.try_wakeup(&sim_state, 0, &mut stations);
+        sim.power_up_down(&sim_state, controller.as_mut(), &mut stations);
         assert!(std::matches!(stations[0].state, BaseStationState::Active));
         assert!(std::matches!(
             stations[1].state,
             BaseStationState::PowerUp(_)
         ));
         assert!(std::matches!(stations[2].state, BaseStationState::Active));
-        assert_eq!(stations[0].get_usage(&sim.cfg), 50.0);
-        assert_eq!(stations[1].get_usage(&sim.cfg), 50.0);
-        assert_eq!(stations[2].get_usage(&sim.cfg), 0.0);
+        assert_eq!(stations[0].get_usage(), 50.0);
+        assert_eq!(stations[1].get_usage(), 50.0);
+        assert_eq!(stations[2].get_usage(), 0.0);
     }
 
     #[test]
@@ -625,41 +1892,47 @@ mod test {
             BaseStation::new(2, &sim.cfg, 1.0, &mut rng),
         ];
         for _ in 0..10 {
-            stations[1].execute_event(
-                &BaseStationEvent::AddUser,
-                &sim.cfg,
-                &mut sim_state,
-                &mut rng,
-                false,
-                &mut logger,
-            );
+            stations[1]
+                .execute_event(
+                    &BaseStationEvent::AddUser,
+                    &sim.cfg,
+                    &mut sim_state,
+                    &mut rng,
+                    false,
+                    &mut logger,
+                )
+                .unwrap();
         }
         for _ in 0..6 {
-            stations[2].execute_event(
-                &BaseStationEvent::AddUser,
-                &sim.cfg,
-                &mut sim_state,
-                &mut rng,
-                false,
-                &mut logger,
-            );
+            stations[2]
+                .execute_event(
+                    &BaseStationEvent::AddUser,
+                    &sim.cfg,
+                    &mut sim_state,
+                    &mut rng,
+                    false,
+                    &mut logger,
+                )
+                .unwrap();
         }
         for _ in 0..4 {
-            stations[0].execute_event(
-                &BaseStationEvent::AddUser,
-                &sim.cfg,
-                &mut sim_state,
-                &mut rng,
-                false,
-                &mut logger,
-            );
+            stations[0]
+                .execute_event(
+                    &BaseStationEvent::AddUser,
+                    &sim.cfg,
+                    &mut sim_state,
+                    &mut rng,
+                    false,
+                    &mut logger,
+                )
+                .unwrap();
         }
 
         // Test shutdown
         // 4 users from station 0 redirected to:
         // - station 1: 10/24 * 4 -> 1 user
         // - station 2: 4 - 1 = 3 users
-        sim.try_shutdown(&sim_state, &mut stations);
+        sim.shut_down(&sim_state, 0, &mut stations);
         assert!(std::matches!(
             stations[0].state,
             BaseStationState::PowerDown(_)
@@ -671,9 +1944,9 @@ mod test {
         assert_eq!(stations[2].get_usage_raw(), 9);
 
         // Test no shutdown when there are less than 2 active stations
-        stations[1].state = BaseStationState::Sleep;
+        stations[1].state = BaseStationState::Sleep(0);
         let _ = stations[2].release_all();
-        sim.try_shutdown(&sim_state, &mut stations);
+        sim.shut_down(&sim_state, 2, &mut stations);
         assert!(std::matches!(
             stations[0].state,
             BaseStationState::PowerDown(_)
@@ -683,30 +1956,86 @@ mod test {
         assert!(std::matches!(stations[2].state, BaseStationState::Active));
         assert_eq!(stations[2].get_usage_raw(), 0);
 
+        // Test cancelled shutdown when wide user does not fit into any station,
+        // narrow user of the same station is not moved either
+        let wide = SimContainer::new_test(3, 10);
+        let mut wide_stations: Vec<BaseStation> = (0..3)
+            .map(|i| BaseStation::new(i, &wide.cfg, 1.0, &mut rng))
+            .collect();
+        for (idx, blocks) in [(0, 6), (0, 1), (1, 5), (2, 5)] {
+            wide_stations[idx]
+                .force_add_user(User::test(idx, 10).with_blocks(blocks).with_class(0));
+        }
+        assert!(wide.shut_down(&sim_state, 0, &mut wide_stations).is_empty());
+        assert!(wide_stations[0].is_active());
+        for (station, usage) in wide_stations.iter().zip([7, 5, 5]) {
+            assert_eq!(station.get_usage_raw(), usage);
+        }
+
+        // Test cancelled shutdown when user in spatial mode is not covered by any neighbour
+        let position = |x: f64, y: f64| Position { x, y };
+        let cfg = Config {
+            stations_count: 3,
+            resources_count: 10,
+            topology: Topology::Coverage {
+                positions: vec![
+                    position(0.0, 0.0),
+                    position(150.0, 0.0),
+                    position(0.0, 150.0),
+                ],
+                radius: 100.0,
+                neighbours: Vec::new(),
+            },
+            spatial: Some(SpatialConfig::default()),
+            ..Default::default()
+        };
+        let spatial = SimulationBuilder::new(cfg).build().unwrap();
+        let mut spatial_stations: Vec<BaseStation> = (0..3)
+            .map(|i| BaseStation::new(i, &spatial.cfg, 1.0, &mut rng))
+            .collect();
+        spatial_stations[0].force_add_user(User::test(0, 10).with_position(75.0, 0.0));
+        spatial_stations[0].force_add_user(User::test(1, 20).with_position(-50.0, 0.0));
+        assert!(spatial
+            .shut_down(&sim_state, 0, &mut spatial_stations)
+            .is_empty());
+        assert!(spatial_stations[0].is_active());
+        assert_eq!(spatial_stations[0].get_usage_raw(), 2);
+        // user covered by neighbour is moved to it
+        let _ = spatial_stations[0].release_where(|user| user.id == 1);
+        assert!(!spatial
+            .shut_down(&sim_state, 0, &mut spatial_stations)
+            .is_empty());
+        let usage: Vec<usize> = spatial_stations.iter().map(|s| s.get_usage_raw()).collect();
+        assert_eq!(usage, vec![0, 1, 0]);
+
         // Test no shutdown when there is not enough space for redirected users
         stations[0].state = BaseStationState::Active;
-        stations[0].execute_event(
-            &BaseStationEvent::AddUser,
-            &sim.cfg,
-            &mut sim_state,
-            &mut rng,
-            false,
-            &mut logger,
-        );
-        for _ in 0..20 {
-            stations[2].execute_event(
+        stations[0]
+            .execute_event(
                 &BaseStationEvent::AddUser,
                 &sim.cfg,
                 &mut sim_state,
                 &mut rng,
                 false,
                 &mut logger,
-            );
+            )
+            .unwrap();
+        for _ in 0..20 {
+            stations[2]
+                .execute_event(
+                    &BaseStationEvent::AddUser,
+                    &sim.cfg,
+                    &mut sim_state,
+                    &mut rng,
+                    false,
+                    &mut logger,
+                )
+                .unwrap();
         }
-        sim.try_shutdown(&sim_state, &mut stations);
+        sim.shut_down(&sim_state, 0, &mut stations);
         assert!(std::matches!(stations[0].state, BaseStationState::Active));
         assert_eq!(stations[0].get_usage_raw(), 1);
-        assert!(std::matches!(stations[1].state, BaseStationState::Sleep));
+        assert!(std::matches!(stations[1].state, BaseStationState::Sleep(_)));
         assert_eq!(stations[1].get_usage_raw(), 11);
         assert!(std::matches!(stations[2].state, BaseStationState::Active));
         assert_eq!(stations[2].get_usage_raw(), 20);
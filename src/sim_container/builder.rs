@@ -0,0 +1,271 @@
+use std::{path::PathBuf, sync::Arc};
+
+use super::{SchedulerKind, SimContainer};
+use crate::{config::Config, error::CassidyError, trace::load_trace};
+
+/// Run options of simulation, independent from simulated system configuration
+#[derive(Debug, Clone)]
+pub struct SimOptions {
+    pub duration: f64, // [h]
+    pub seed: Option<u64>,
+    pub iterations: u32,
+    pub enable_sleep: bool,
+    pub log: bool,
+    pub log_wave: bool,
+    pub samples: usize,
+    pub show_partial_results: bool,
+    pub log_dir: PathBuf,
+    pub scheduler: SchedulerKind,
+    pub confidence_level: f64,
+}
+
+impl Default for SimOptions {
+    fn default() -> Self {
+        Self {
+            duration: 24.0,
+            seed: None,
+            iterations: 1,
+            enable_sleep: false,
+            log: false,
+            log_wave: false,
+            samples: 1,
+            show_partial_results: false,
+            log_dir: PathBuf::from("."),
+            scheduler: SchedulerKind::default(),
+            confidence_level: 0.95,
+        }
+    }
+}
+
+impl SimOptions {
+    pub fn validate(self) -> Result<SimOptions, CassidyError> {
+        if self.duration <= 0.0 {
+            return Err(CassidyError::Usage(
+                "Duration must be greater than 0".to_owned(),
+            ));
+        }
+        if self.iterations == 0 {
+            return Err(CassidyError::Usage(
+                "Iterations must be greater than 0".to_owned(),
+            ));
+        }
+        if self.confidence_level <= 0.0 || self.confidence_level >= 1.0 {
+            return Err(CassidyError::Usage(
+                "Confidence level must be from range (0, 1)".to_owned(),
+            ));
+        }
+        Ok(self)
+    }
+}
+
+/// Creates [`SimContainer`] from given config and run options.
+///
+/// ```no_run
+/// use cassidy::{Config, SimulationBuilder};
+///
+/// let scene = SimulationBuilder::new(Config::default())
+///     .duration(24.0)
+///     .seed(1)
+///     .iterations(10)
+///     .enable_sleep(true)
+///     .build()
+///     .unwrap();
+/// let results = scene.run(0).unwrap();
+/// println!("{}", results.get_report());
+/// ```
+#[derive(Debug, Clone)]
+pub struct SimulationBuilder {
+    cfg: Config,
+    options: SimOptions,
+}
+
+impl SimulationBuilder {
+    pub fn new(cfg: Config) -> SimulationBuilder {
+        SimulationBuilder {
+            cfg,
+            options: SimOptions::default(),
+        }
+    }
+
+    /// Replaces all run options at once
+    pub fn options(mut self, options: SimOptions) -> SimulationBuilder {
+        self.options = options;
+        self
+    }
+
+    /// Time (in hours) simulation will be run for
+    pub fn duration(mut self, duration: f64) -> SimulationBuilder {
+        self.options.duration = duration;
+        self
+    }
+
+    /// Seed for random number generator. Each iteration uses seed + iteration number
+    pub fn seed(mut self, seed: u64) -> SimulationBuilder {
+        self.options.seed = Some(seed);
+        self
+    }
+
+    pub fn iterations(mut self, iterations: u32) -> SimulationBuilder {
+        self.options.iterations = iterations;
+        self
+    }
+
+    pub fn enable_sleep(mut self, enable: bool) -> SimulationBuilder {
+        self.options.enable_sleep = enable;
+        self
+    }
+
+    /// Generate event log file for each iteration
+    pub fn log(mut self, enable: bool) -> SimulationBuilder {
+        self.options.log = enable;
+        self
+    }
+
+    /// Log simulation process in binary format
+    pub fn log_wave(mut self, enable: bool) -> SimulationBuilder {
+        self.options.log_wave = enable;
+        self
+    }
+
+    /// Binary log sampling divider
+    pub fn samples(mut self, samples: usize) -> SimulationBuilder {
+        self.options.samples = samples;
+        self
+    }
+
+    /// Print partial results from all iterations and save them to separate file
+    pub fn show_partial_results(mut self, enable: bool) -> SimulationBuilder {
+        self.options.show_partial_results = enable;
+        self
+    }
+
+    /// Directory where all log files are created
+    pub fn log_dir(mut self, path: PathBuf) -> SimulationBuilder {
+        self.options.log_dir = path;
+        self
+    }
+
+    /// Strategy used to find the next event. Both strategies give identical results
+    pub fn scheduler(mut self, scheduler: SchedulerKind) -> SimulationBuilder {
+        self.options.scheduler = scheduler;
+        self
+    }
+
+    /// Level of confidence intervals calculated across iterations, e.g. 0.95
+    pub fn confidence_level(mut self, level: f64) -> SimulationBuilder {
+        self.options.confidence_level = level;
+        self
+    }
+
+    /// Time (in hours) from the beginning of simulation, which is excluded from statistics.
+    /// Overrides warmup from config
+    pub fn warmup(mut self, warmup: f64) -> SimulationBuilder {
+        self.cfg.warmup = warmup;
+        self
+    }
+
+    /// Detect warm-up period automatically with pilot simulation
+    pub fn auto_warmup(mut self, enable: bool) -> SimulationBuilder {
+        self.cfg.auto_warmup = enable;
+        self
+    }
+
+    pub fn build(self) -> Result<SimContainer, CassidyError> {
+        let options = self.options.validate()?;
+        let mut cfg = self.cfg.validate()?;
+        cfg.load_service_times()?;
+        if cfg.warmup > 0.0 && cfg.warmup >= options.duration {
+            return Err(CassidyError::InvalidConfig(
+                "warmup must be shorter than simulation duration".to_owned(),
+            ));
+        }
+        let trace = match &cfg.trace {
+            Some(path) => Some(Arc::new(load_trace(path, cfg.stations_count)?)),
+            None => None,
+        };
+        cfg.load_lambda_profiles()?;
+        cfg.load_density_map()?;
+        cfg.topology.prepare();
+        // convert lambda timestamps from hours to microseconds
+        for p in cfg.lambda_coefs.iter_mut() {
+            p.time *= 3600.0 * 1_000_000.0;
+        }
+        cfg.lambda_profile.scale_time(3600.0 * 1_000_000.0);
+        for station in cfg.stations.iter_mut() {
+            if let Some(profile) = station.lambda_profile.as_mut() {
+                profile.scale_time(3600.0 * 1_000_000.0);
+            }
+        }
+        Ok(SimContainer {
+            options,
+            cfg,
+            trace,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SimOptions, SimulationBuilder};
+    use crate::{config::Config, error::CassidyError};
+
+    #[test]
+    fn build_and_run() {
+        let cfg = Config {
+            stations_count: 3,
+            resources_count: 20,
+            ..Default::default()
+        };
+        let builder = SimulationBuilder::new(cfg)
+            .duration(0.1)
+            .seed(1)
+            .iterations(2)
+            .enable_sleep(true);
+        let res = builder.clone().build().unwrap().run(0).unwrap();
+        assert_eq!(res.stations.len(), 3);
+        assert!(res.total_users > 0);
+        assert_eq!(res.spread.as_ref().unwrap().iterations, 2);
+        // the same seed gives the same results
+        let again = builder.build().unwrap().run(0).unwrap();
+        assert_eq!(res.total_users, again.total_users);
+        assert_eq!(res.average_power, again.average_power);
+    }
+
+    #[test]
+    fn invalid_options() {
+        let invalid = [
+            SimOptions {
+                duration: 0.0,
+                ..Default::default()
+            },
+            SimOptions {
+                duration: -1.0,
+                ..Default::default()
+            },
+            SimOptions {
+                iterations: 0,
+                ..Default::default()
+            },
+            SimOptions {
+                confidence_level: 1.0,
+                ..Default::default()
+            },
+        ];
+        for options in invalid {
+            let err = SimulationBuilder::new(Config::default())
+                .options(options)
+                .build()
+                .err()
+                .unwrap();
+            assert!(matches!(err, CassidyError::Usage(_)), "{}", err);
+            assert_eq!(err.exit_code(), 64);
+        }
+        let err = SimulationBuilder::new(Config::default())
+            .duration(1.0)
+            .warmup(2.0)
+            .build()
+            .err()
+            .unwrap();
+        assert!(matches!(err, CassidyError::InvalidConfig(_)), "{}", err);
+    }
+}
@@ -0,0 +1,161 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use crate::{basestation::BaseStation, error::CassidyError};
+
+/// Number of stations from which queue is faster than scan, measured with `benches/scheduler.rs`
+const QUEUE_MIN_STATIONS: usize = 25;
+
+/// Strategy used by simulation loop to find the next event
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SchedulerKind {
+    /// Scan for less than 25 stations, where its lower overhead pays off, queue otherwise
+    #[default]
+    Auto,
+    /// Ask every station for its next event. O(N) per event
+    Scan,
+    /// Keep next event of every station in single priority queue. O(log N) per event
+    Queue,
+}
+
+/// Finds station with the earliest event.
+/// Each station that was modified outside of `next` must be reported with `update`,
+/// otherwise queue based scheduler will work on outdated events.
+#[derive(Debug)]
+pub enum EventScheduler {
+    Scan,
+    Queue(EventQueue),
+}
+
+impl EventScheduler {
+    pub fn new(kind: SchedulerKind, stations: &[BaseStation]) -> EventScheduler {
+        match kind {
+            SchedulerKind::Auto if stations.len() < QUEUE_MIN_STATIONS => EventScheduler::Scan,
+            SchedulerKind::Scan => EventScheduler::Scan,
+            SchedulerKind::Auto | SchedulerKind::Queue => {
+                EventScheduler::Queue(EventQueue::new(stations))
+            }
+        }
+    }
+
+    /// Returns timestamp of the earliest event and index of station it belongs to.
+    /// When several stations have event at the same time, station with lowest index is returned.
+    pub fn next(&mut self, stations: &[BaseStation]) -> Result<(u64, usize), CassidyError> {
+        match self {
+            EventScheduler::Scan => {
+                let mut event_station: usize = 0;
+                let (mut next_event_time, _) = stations[0].get_next_event();
+                for (i, station) in stations[1..].iter().enumerate() {
+                    let (time, _) = station.get_next_event();
+                    if time < next_event_time {
+                        next_event_time = time;
+                        event_station = i + 1; // offset from iteration range
+                    }
+                }
+                Ok((next_event_time, event_station))
+            }
+            EventScheduler::Queue(queue) => queue.next(),
+        }
+    }
+
+    /// Reschedules event of station with given index
+    pub fn update(&mut self, idx: usize, stations: &[BaseStation]) {
+        if let EventScheduler::Queue(queue) = self {
+            queue.update(idx, stations);
+        }
+    }
+}
+
+/// Priority queue of stations events with lazy invalidation.
+/// Every update of station pushes new entry with increased generation number,
+/// and outdated entries are discarded when they reach the top of the queue.
+#[derive(Debug)]
+pub struct EventQueue {
+    // (timestamp, station index, generation)
+    heap: BinaryHeap<Reverse<(u64, usize, u64)>>,
+    generations: Vec<u64>,
+}
+
+impl EventQueue {
+    fn new(stations: &[BaseStation]) -> EventQueue {
+        let mut queue = EventQueue {
+            heap: BinaryHeap::with_capacity(stations.len() * 2),
+            generations: vec![0; stations.len()],
+        };
+        queue.rebuild(stations);
+        queue
+    }
+
+    fn rebuild(&mut self, stations: &[BaseStation]) {
+        self.heap.clear();
+        for (idx, station) in stations.iter().enumerate() {
+            let (time, _) = station.get_next_event();
+            self.heap.push(Reverse((time, idx, self.generations[idx])));
+        }
+    }
+
+    fn next(&mut self) -> Result<(u64, usize), CassidyError> {
+        while let Some(&Reverse((time, idx, generation))) = self.heap.peek() {
+            if generation == self.generations[idx] {
+                return Ok((time, idx));
+            }
+            self.heap.pop();
+        }
+        Err(CassidyError::Internal("event queue is empty".to_owned()))
+    }
+
+    fn update(&mut self, idx: usize, stations: &[BaseStation]) {
+        self.generations[idx] += 1;
+        // drop outdated entries when they start to dominate the queue
+        if self.heap.len() > 4 * stations.len() {
+            self.rebuild(stations);
+            return;
+        }
+        let (time, _) = stations[idx].get_next_event();
+        self.heap.push(Reverse((time, idx, self.generations[idx])));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{EventScheduler, SchedulerKind};
+    use crate::{basestation::BaseStation, config::Config, user::User};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn queue_matches_scan() {
+        let cfg = Config::default();
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut stations: Vec<BaseStation> = (0..5)
+            .map(|i| BaseStation::new(i, &cfg, 1.0, &mut rng))
+            .collect();
+        let mut scan = EventScheduler::new(SchedulerKind::Scan, &stations);
+        let mut queue = EventScheduler::new(SchedulerKind::Queue, &stations);
+        assert!(matches!(
+            EventScheduler::new(SchedulerKind::Auto, &stations),
+            EventScheduler::Scan
+        ));
+        assert_eq!(
+            scan.next(&stations).unwrap(),
+            queue.next(&stations).unwrap()
+        );
+
+        // same timestamps in different stations -> lowest index wins
+        stations[3].next_user_add = 5;
+        stations[1].next_user_add = 5;
+        queue.update(3, &stations);
+        queue.update(1, &stations);
+        assert_eq!(scan.next(&stations).unwrap(), (5, 1));
+        assert_eq!(queue.next(&stations).unwrap(), (5, 1));
+
+        // outdated entry of station 1 must be skipped
+        stations[1].next_user_add = 100;
+        stations[1].force_add_user(User::test(0, 2));
+        queue.update(1, &stations);
+        assert_eq!(scan.next(&stations).unwrap(), (2, 1));
+        assert_eq!(queue.next(&stations).unwrap(), (2, 1));
+        stations[1].release_all();
+        queue.update(1, &stations);
+        assert_eq!(scan.next(&stations).unwrap(), (5, 3));
+        assert_eq!(queue.next(&stations).unwrap(), (5, 3));
+    }
+}
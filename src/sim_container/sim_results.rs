@@ -1,32 +1,213 @@
 use crate::basestation::BaseStationResult;
-use crate::config::Config;
+use crate::config::{Config, Topology};
+use crate::stats::MetricStats;
 use core::iter::zip;
 
+/// Spread of every metric (in order of [`SimResults::metrics`]) across simulation iterations
+#[derive(Debug, Clone)]
+pub struct ResultsSpread {
+    pub iterations: usize,
+    pub confidence_level: f64,
+    pub metrics: Vec<MetricStats>,
+}
+
+/// Arrivals and blocking of single service class
+#[derive(Debug)]
+pub struct ClassResult {
+    pub name: String,
+    pub total_users: usize,
+    pub dropped_users: usize,
+    pub blocking_rate: f64, // [0-100]%
+}
+
+/// Arrivals, admissions, preemptions and drops of single priority level.
+/// Admitted users got resources on arrival, directly, by preemption or by redirect.
+/// Dropped users are blocked arrivals only, preempted users, which could not be redirected,
+/// are counted only as preempted
+#[derive(Debug)]
+pub struct PriorityResult {
+    pub total_users: usize,
+    pub admitted_users: usize,
+    pub preempted_users: usize,
+    pub dropped_users: usize,
+    pub drop_rate: f64, // [0-100]%
+}
+
+/// Statistics of waiting queues
+#[derive(Debug)]
+pub struct QueueResult {
+    pub queued_users: usize,
+    pub abandoned_users: usize,
+    pub abandonment_rate: f64,     // [0-100]% of queued users
+    pub average_wait: f64,         // [ms], of users admitted from queue
+    pub average_queue_length: f64, // time average of all waiting users
+}
+
+/// Energy consumption weighted by hourly schedule, i.e. its cost or emissions,
+/// compared with always-on baseline of the same run.
+/// Baseline charges every station power of active station at usage it had at given moment,
+/// also while it was sleeping or transitioning. Users moved by sleep mode are charged
+/// to the station that served them, so baseline is network-wide counterpart of the run
+/// with the same distribution of load, not of a run in which every station serves its own users.
+#[derive(Debug)]
+pub struct AccountResult {
+    pub total: f64,
+    pub stations: Vec<f64>,
+    pub baseline: f64,
+    pub savings: f64, // [0-100]% of baseline saved by sleep mode
+}
+
+impl AccountResult {
+    pub(crate) fn new(total: f64, stations: Vec<f64>, baseline: f64) -> AccountResult {
+        let mut account = AccountResult {
+            total,
+            stations,
+            baseline,
+            savings: 0.0,
+        };
+        account.update_savings();
+        account
+    }
+
+    fn new_zero(stations_count: usize) -> AccountResult {
+        AccountResult::new(0.0, vec![0.0; stations_count], 0.0)
+    }
+
+    fn update_savings(&mut self) {
+        self.savings = if self.baseline > 0.0 {
+            (1.0 - self.total / self.baseline) * 100.0
+        } else {
+            0.0
+        };
+    }
+
+    fn add(&mut self, x: &AccountResult) {
+        self.total += x.total;
+        self.baseline += x.baseline;
+        for (s, partial) in zip(self.stations.iter_mut(), x.stations.iter()) {
+            *s += partial;
+        }
+    }
+
+    /// Averages totals, savings are recalculated from averaged totals
+    fn div(&mut self, x: f64) {
+        self.total /= x;
+        self.baseline /= x;
+        self.update_savings();
+        for s in self.stations.iter_mut() {
+            *s /= x;
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct SimResults {
     pub average_usage: f64,
     pub average_power: f64,
+    pub total_energy: f64, // [kWh], of all stations
     pub average_drop_rate: f64,
     pub total_users: usize,
     pub dropped_users: usize,
+    /// Dropped users, for which no neighbouring station had free resources.
+    /// Present only when topology is configured
+    pub neighbour_blocked_users: Option<usize>,
+    /// Dropped users, whose position was not covered by any awake station.
+    /// Present only in spatial mode
+    pub uncovered_users: Option<usize>,
+    /// Users moved between stations and users dropped during handover,
+    /// which are not included in dropped_users. Present only when users move in spatial mode
+    pub handovers: Option<usize>,
+    pub dropped_calls: Option<usize>,
+    /// Preempted users, which could not be redirected or queued, and were terminated.
+    /// They are not included in dropped_users. Present only when preemption is enabled
+    pub preempted_dropped: Option<usize>,
+    /// Arrivals refused by admission control of their station, although they fitted into free resources.
+    /// They are redirected or dropped as other blocked users. Present only when admission control is configured
+    pub admission_blocked: Option<usize>,
+    /// Present only when queue is configured. Users waiting in queue are not included in dropped_users
+    pub queue: Option<QueueResult>,
+    /// Cost of energy by configured tariff and emissions [kg CO2] by configured carbon intensity.
+    /// Present only when the schedule is configured
+    pub cost: Option<AccountResult>,
+    pub emissions: Option<AccountResult>,
     pub stations: Vec<BaseStationResult>,
+    /// Names of configured sleep levels. Time spent in every level is reported only when levels are configured
+    pub sleep_levels: Vec<String>,
+    pub classes: Vec<ClassResult>,
+    /// Results of priority levels, ordered by priority. Empty when priority levels are not configured
+    pub priorities: Vec<PriorityResult>,
+    pub spread: Option<ResultsSpread>,
+    /// Warm-up period [h] detected by pilot run. Present only when automatic detection is enabled
+    pub detected_warmup: Option<f64>,
 }
 
 impl SimResults {
     pub fn new_zero(cfg: &Config) -> SimResults {
+        let mobile = cfg.spatial.as_ref().is_some_and(|s| s.is_mobile());
         let mut res = SimResults {
             average_usage: 0.0,
             average_power: 0.0,
+            total_energy: 0.0,
             average_drop_rate: 0.0,
             total_users: 0,
             dropped_users: 0,
+            neighbour_blocked_users: (cfg.topology != Topology::Full).then_some(0),
+            uncovered_users: cfg.spatial.is_some().then_some(0),
+            handovers: mobile.then_some(0),
+            dropped_calls: mobile.then_some(0),
+            preempted_dropped: cfg
+                .priorities
+                .as_ref()
+                .is_some_and(|p| p.preemption)
+                .then_some(0),
+            admission_blocked: cfg.has_admission_control().then_some(0),
+            queue: cfg.queue.as_ref().map(|_| QueueResult {
+                queued_users: 0,
+                abandoned_users: 0,
+                abandonment_rate: 0.0,
+                average_wait: 0.0,
+                average_queue_length: 0.0,
+            }),
+            cost: cfg
+                .tariff
+                .as_ref()
+                .map(|_| AccountResult::new_zero(cfg.stations_count)),
+            emissions: cfg
+                .carbon_intensity
+                .as_ref()
+                .map(|_| AccountResult::new_zero(cfg.stations_count)),
             stations: Vec::new(),
+            sleep_levels: cfg.sleep_levels.iter().map(|l| l.name.clone()).collect(),
+            classes: Vec::new(),
+            priorities: Vec::new(),
+            spread: None,
+            detected_warmup: None,
         };
-        for _ in 0..cfg.stations_count {
+        for i in 0..cfg.stations_count {
             res.stations.push(BaseStationResult {
                 average_power: 0.0,
                 average_usage: 0.0,
                 average_sleep_time: 0.0,
+                sleep_level_times: vec![0.0; cfg.station_params(i).sleep_levels.len()],
+                energy: 0.0,
+            })
+        }
+        for class in cfg.service_classes.iter() {
+            res.classes.push(ClassResult {
+                name: class.name.clone(),
+                total_users: 0,
+                dropped_users: 0,
+                blocking_rate: 0.0,
+            })
+        }
+        let levels = cfg.priorities.as_ref().map_or(0, |p| p.shares.len());
+        for _ in 0..levels {
+            res.priorities.push(PriorityResult {
+                total_users: 0,
+                admitted_users: 0,
+                preempted_users: 0,
+                dropped_users: 0,
+                drop_rate: 0.0,
             })
         }
         res
@@ -35,28 +216,244 @@ impl SimResults {
     pub fn add(&mut self, x: &SimResults) {
         self.average_usage += x.average_usage;
         self.average_power += x.average_power;
+        self.total_energy += x.total_energy;
         self.average_drop_rate += x.average_drop_rate;
         self.total_users += x.total_users;
         self.dropped_users += x.dropped_users;
+        if let (Some(blocked), Some(partial)) = (
+            self.neighbour_blocked_users.as_mut(),
+            x.neighbour_blocked_users,
+        ) {
+            *blocked += partial;
+        }
+        if let (Some(uncovered), Some(partial)) = (self.uncovered_users.as_mut(), x.uncovered_users)
+        {
+            *uncovered += partial;
+        }
+        for (total, partial) in [
+            (self.handovers.as_mut(), x.handovers),
+            (self.dropped_calls.as_mut(), x.dropped_calls),
+            (self.preempted_dropped.as_mut(), x.preempted_dropped),
+            (self.admission_blocked.as_mut(), x.admission_blocked),
+        ] {
+            if let (Some(total), Some(partial)) = (total, partial) {
+                *total += partial;
+            }
+        }
+        if let (Some(queue), Some(partial)) = (self.queue.as_mut(), x.queue.as_ref()) {
+            queue.queued_users += partial.queued_users;
+            queue.abandoned_users += partial.abandoned_users;
+            queue.abandonment_rate += partial.abandonment_rate;
+            queue.average_wait += partial.average_wait;
+            queue.average_queue_length += partial.average_queue_length;
+        }
+        for (total, partial) in [
+            (self.cost.as_mut(), x.cost.as_ref()),
+            (self.emissions.as_mut(), x.emissions.as_ref()),
+        ] {
+            if let (Some(total), Some(partial)) = (total, partial) {
+                total.add(partial);
+            }
+        }
         for (s, partial) in zip(self.stations.iter_mut(), x.stations.iter()) {
             s.average_power += partial.average_power;
             s.average_usage += partial.average_usage;
             s.average_sleep_time += partial.average_sleep_time;
+            s.energy += partial.energy;
+            for (t, partial) in zip(
+                s.sleep_level_times.iter_mut(),
+                partial.sleep_level_times.iter(),
+            ) {
+                *t += partial;
+            }
+        }
+        for (c, partial) in zip(self.classes.iter_mut(), x.classes.iter()) {
+            c.total_users += partial.total_users;
+            c.dropped_users += partial.dropped_users;
+            c.blocking_rate += partial.blocking_rate;
+        }
+        for (p, partial) in zip(self.priorities.iter_mut(), x.priorities.iter()) {
+            p.total_users += partial.total_users;
+            p.admitted_users += partial.admitted_users;
+            p.preempted_users += partial.preempted_users;
+            p.dropped_users += partial.dropped_users;
+            p.drop_rate += partial.drop_rate;
         }
     }
     pub fn div(&mut self, x: f64) {
         self.average_usage /= x;
         self.average_power /= x;
+        self.total_energy /= x;
         self.average_drop_rate /= x;
         self.total_users = (self.total_users as f64 / x) as usize;
         self.dropped_users = (self.dropped_users as f64 / x) as usize;
+        if let Some(blocked) = self.neighbour_blocked_users.as_mut() {
+            *blocked = (*blocked as f64 / x) as usize;
+        }
+        if let Some(uncovered) = self.uncovered_users.as_mut() {
+            *uncovered = (*uncovered as f64 / x) as usize;
+        }
+        for total in [
+            self.handovers.as_mut(),
+            self.dropped_calls.as_mut(),
+            self.preempted_dropped.as_mut(),
+            self.admission_blocked.as_mut(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *total = (*total as f64 / x) as usize;
+        }
+        if let Some(queue) = self.queue.as_mut() {
+            queue.queued_users = (queue.queued_users as f64 / x) as usize;
+            queue.abandoned_users = (queue.abandoned_users as f64 / x) as usize;
+            queue.abandonment_rate /= x;
+            queue.average_wait /= x;
+            queue.average_queue_length /= x;
+        }
+        for account in [self.cost.as_mut(), self.emissions.as_mut()]
+            .into_iter()
+            .flatten()
+        {
+            account.div(x);
+        }
         for s in self.stations.iter_mut() {
             s.average_power /= x;
             s.average_usage /= x;
             s.average_sleep_time /= x;
+            s.energy /= x;
+            for t in s.sleep_level_times.iter_mut() {
+                *t /= x;
+            }
+        }
+        for c in self.classes.iter_mut() {
+            c.total_users = (c.total_users as f64 / x) as usize;
+            c.dropped_users = (c.dropped_users as f64 / x) as usize;
+            c.blocking_rate /= x;
+        }
+        for p in self.priorities.iter_mut() {
+            p.total_users = (p.total_users as f64 / x) as usize;
+            p.admitted_users = (p.admitted_users as f64 / x) as usize;
+            p.preempted_users = (p.preempted_users as f64 / x) as usize;
+            p.dropped_users = (p.dropped_users as f64 / x) as usize;
+            p.drop_rate /= x;
         }
     }
 
+    /// Averages results of all iterations. When there are at least 2 iterations,
+    /// spread of every metric with confidence interval at given level is also calculated.
+    pub fn aggregate(cfg: &Config, partials: &[SimResults], confidence_level: f64) -> SimResults {
+        let mut res = SimResults::new_zero(cfg);
+        for partial in partials.iter() {
+            res.add(partial);
+        }
+        res.div(partials.len() as f64);
+        if partials.len() < 2 {
+            return res;
+        }
+        let samples: Vec<Vec<(String, f64)>> = partials.iter().map(|p| p.metrics()).collect();
+        let metrics = (0..samples[0].len())
+            .map(|i| {
+                let values: Vec<f64> = samples.iter().map(|m| m[i].1).collect();
+                MetricStats::from_samples(&values, confidence_level)
+            })
+            .collect();
+        res.spread = Some(ResultsSpread {
+            iterations: partials.len(),
+            confidence_level,
+            metrics,
+        });
+        res
+    }
+
+    /// Returns names and values of all metrics, in the same order as in CSV output
+    pub fn metrics(&self) -> Vec<(String, f64)> {
+        let mut metrics = vec![
+            ("processed_users".to_string(), self.total_users as f64),
+            ("dropped_users".to_string(), self.dropped_users as f64),
+            ("average_resource_usage".to_string(), self.average_usage),
+            ("average_power_consumption".to_string(), self.average_power),
+            ("average_user_drop_rate".to_string(), self.average_drop_rate),
+            ("total_energy".to_string(), self.total_energy),
+        ];
+        if let Some(blocked) = self.neighbour_blocked_users {
+            metrics.push(("neighbour_blocked_users".to_string(), blocked as f64));
+        }
+        if let Some(uncovered) = self.uncovered_users {
+            metrics.push(("uncovered_users".to_string(), uncovered as f64));
+        }
+        if let (Some(handovers), Some(dropped)) = (self.handovers, self.dropped_calls) {
+            metrics.push(("handovers".to_string(), handovers as f64));
+            metrics.push(("dropped_calls".to_string(), dropped as f64));
+        }
+        if let Some(dropped) = self.preempted_dropped {
+            metrics.push(("preempted_dropped_users".to_string(), dropped as f64));
+        }
+        if let Some(blocked) = self.admission_blocked {
+            metrics.push(("admission_blocked_users".to_string(), blocked as f64));
+        }
+        if let Some(queue) = &self.queue {
+            metrics.push(("queued_users".to_string(), queue.queued_users as f64));
+            metrics.push(("abandoned_users".to_string(), queue.abandoned_users as f64));
+            metrics.push(("abandonment_rate".to_string(), queue.abandonment_rate));
+            metrics.push(("average_wait".to_string(), queue.average_wait));
+            metrics.push((
+                "average_queue_length".to_string(),
+                queue.average_queue_length,
+            ));
+        }
+        for (name, account) in [("cost", &self.cost), ("emissions", &self.emissions)] {
+            if let Some(account) = account {
+                metrics.push((format!("total_{}", name), account.total));
+                metrics.push((format!("baseline_{}", name), account.baseline));
+                metrics.push((format!("{}_savings", name), account.savings));
+            }
+        }
+        for (i, station) in self.stations.iter().enumerate() {
+            metrics.push((format!("station{}_average_power", i), station.average_power));
+            metrics.push((format!("station{}_average_usage", i), station.average_usage));
+            metrics.push((
+                format!("station{}_average_sleep_time", i),
+                station.average_sleep_time,
+            ));
+            metrics.push((format!("station{}_energy", i), station.energy));
+        }
+        for (name, account) in [("cost", &self.cost), ("emissions", &self.emissions)] {
+            if let Some(account) = account {
+                for (i, value) in account.stations.iter().enumerate() {
+                    metrics.push((format!("station{}_{}", i, name), *value));
+                }
+            }
+        }
+        for (i, station) in self.stations.iter().enumerate() {
+            for (name, time) in zip(self.sleep_levels.iter(), station.sleep_level_times.iter()) {
+                metrics.push((format!("station{}_{}_time", i, name), *time));
+            }
+        }
+        for class in self.classes.iter() {
+            metrics.push((format!("{}_users", class.name), class.total_users as f64));
+            metrics.push((
+                format!("{}_dropped", class.name),
+                class.dropped_users as f64,
+            ));
+            metrics.push((format!("{}_blocking_rate", class.name), class.blocking_rate));
+        }
+        for (i, level) in self.priorities.iter().enumerate() {
+            metrics.push((format!("priority{}_users", i), level.total_users as f64));
+            metrics.push((
+                format!("priority{}_admitted", i),
+                level.admitted_users as f64,
+            ));
+            metrics.push((
+                format!("priority{}_preempted", i),
+                level.preempted_users as f64,
+            ));
+            metrics.push((format!("priority{}_dropped", i), level.dropped_users as f64));
+            metrics.push((format!("priority{}_drop_rate", i), level.drop_rate));
+        }
+        metrics
+    }
+
     fn pad(s: String, n: usize) -> String {
         format!("{:^n$}", s)
     }
@@ -68,59 +465,204 @@ impl SimResults {
             - dropped users: {} \n\
             - average resource usage: {:.2} %\n\
             - average power consumption: {:.2} W\n\
-            - average user drop rate: {:.2} %\n\
-            \n\
-            Stations results:\n\
-            id  | average power [W] | average usage [%] | average sleep time [%]\n\
-            ----+-------------------+-------------------+-----------------------\n",
+            - total energy consumption: {:.3} kWh\n\
+            - average user drop rate: {:.2} %\n",
             self.total_users,
             self.dropped_users,
             self.average_usage,
             self.average_power,
+            self.total_energy,
             self.average_drop_rate
         );
+        if let Some(blocked) = self.neighbour_blocked_users {
+            msg += &format!("- users dropped without free neighbour: {} \n", blocked);
+        }
+        if let Some(uncovered) = self.uncovered_users {
+            msg += &format!("- users dropped outside coverage: {} \n", uncovered);
+        }
+        if let (Some(handovers), Some(dropped)) = (self.handovers, self.dropped_calls) {
+            msg += &format!("- handovers: {} \n", handovers);
+            msg += &format!("- dropped calls: {} \n", dropped);
+        }
+        if let Some(dropped) = self.preempted_dropped {
+            msg += &format!("- preempted users dropped: {} \n", dropped);
+        }
+        if let Some(blocked) = self.admission_blocked {
+            msg += &format!("- users refused by admission control: {} \n", blocked);
+        }
+        if let Some(queue) = &self.queue {
+            msg += &format!(
+                "- queued users: {} \n\
+                - abandoned users: {} \n\
+                - abandonment rate: {:.2} % \n\
+                - average waiting time: {:.2} ms \n\
+                - average queue length: {:.2} \n",
+                queue.queued_users,
+                queue.abandoned_users,
+                queue.abandonment_rate,
+                queue.average_wait,
+                queue.average_queue_length
+            );
+        }
+        if let Some(cost) = &self.cost {
+            msg += &format!(
+                "- total energy cost: {:.2} (always-on: {:.2}, saved {:.2} %)\n",
+                cost.total, cost.baseline, cost.savings
+            );
+        }
+        if let Some(emissions) = &self.emissions {
+            msg += &format!(
+                "- total emissions: {:.3} kg CO2 (always-on: {:.3}, saved {:.2} %)\n",
+                emissions.total, emissions.baseline, emissions.savings
+            );
+        }
+        msg += "\n\
+            Stations results:\n\
+            id  | average power [W] | average usage [%] | average sleep time [%] | energy [Wh]\n\
+            ----+-------------------+-------------------+------------------------+------------\n";
         for (i, station) in self.stations.iter().enumerate() {
             msg += (format!(
-                "{} | {} | {} | {}\n",
+                "{} | {} | {} | {} | {}\n",
                 Self::pad(format!("{}", i), 3),
                 Self::pad(format!("{:.2}", station.average_power), 17),
                 Self::pad(format!("{:.2}", station.average_usage), 17),
-                Self::pad(format!("{:.2}", station.average_sleep_time * 100.0), 21)
+                Self::pad(format!("{:.2}", station.average_sleep_time * 100.0), 22),
+                Self::pad(format!("{:.2}", station.energy), 11)
             ))
             .as_str();
         }
+        if !self.sleep_levels.is_empty() {
+            msg += "\nTime spent in sleep levels [%]:\nid  ";
+            let mut line = String::from("----");
+            for name in self.sleep_levels.iter() {
+                let width = name.len().max(8);
+                msg += &format!("| {} ", Self::pad(name.clone(), width));
+                line += &format!("+{}", "-".repeat(width + 2));
+            }
+            msg += &format!("\n{}\n", line);
+            for (i, station) in self.stations.iter().enumerate() {
+                msg += &Self::pad(format!("{}", i), 3);
+                for (name, time) in zip(self.sleep_levels.iter(), station.sleep_level_times.iter())
+                {
+                    let width = name.len().max(8);
+                    msg += &format!(" | {}", Self::pad(format!("{:.2}", time * 100.0), width));
+                }
+                msg += "\n";
+            }
+        }
+        if self.cost.is_some() || self.emissions.is_some() {
+            msg += "\n\
+                Stations cost and emissions:\n\
+                id  |    cost    | emissions [kg CO2]\n\
+                ----+------------+-------------------\n";
+            let value = |account: &Option<AccountResult>, i: usize, precision: usize| {
+                account.as_ref().map_or("-".to_string(), |a| {
+                    format!("{:.precision$}", a.stations[i])
+                })
+            };
+            for i in 0..self.stations.len() {
+                msg += &format!(
+                    "{} | {} | {}\n",
+                    Self::pad(format!("{}", i), 3),
+                    Self::pad(value(&self.cost, i, 2), 10),
+                    Self::pad(value(&self.emissions, i, 3), 18)
+                );
+            }
+        }
+        if !self.classes.is_empty() {
+            msg += "\n\
+                Service classes results:\n\
+                class                | processed users | dropped users | blocking rate [%]\n\
+                ---------------------+-----------------+---------------+------------------\n";
+            for class in self.classes.iter() {
+                msg += &format!(
+                    "{:<20} | {} | {} | {}\n",
+                    class.name,
+                    Self::pad(format!("{}", class.total_users), 15),
+                    Self::pad(format!("{}", class.dropped_users), 13),
+                    Self::pad(format!("{:.2}", class.blocking_rate), 17)
+                );
+            }
+        }
+        if !self.priorities.is_empty() {
+            msg += "\n\
+                Priority levels results:\n\
+                priority | processed users | admitted users | preempted users | dropped users | drop rate [%]\n\
+                ---------+-----------------+----------------+-----------------+---------------+--------------\n";
+            for (i, level) in self.priorities.iter().enumerate() {
+                msg += &format!(
+                    "{} | {} | {} | {} | {} | {}\n",
+                    Self::pad(format!("{}", i), 8),
+                    Self::pad(format!("{}", level.total_users), 15),
+                    Self::pad(format!("{}", level.admitted_users), 14),
+                    Self::pad(format!("{}", level.preempted_users), 15),
+                    Self::pad(format!("{}", level.dropped_users), 13),
+                    Self::pad(format!("{:.2}", level.drop_rate), 13)
+                );
+            }
+        }
+        if let Some(spread) = &self.spread {
+            msg += &self.get_spread_report(spread);
+        }
         msg
     }
 
-    pub fn get_csv_header(&self) -> String {
+    fn get_spread_report(&self, spread: &ResultsSpread) -> String {
         let mut msg = format!(
-            "processed_users,dropped_users,average_resource_usage,average_power_consumption,average_user_drop_rate"
+            "\n\
+            Spread over {} iterations ({}% confidence interval):\n\
+            metric                         |    mean    |  std dev   |    min     |    max     |   CI low   |  CI high\n\
+            -------------------------------+------------+------------+------------+------------+------------+-----------\n",
+            spread.iterations,
+            spread.confidence_level * 100.0
         );
-        for i in 0..self.stations.len() {
-            msg += (format!(
-                ",station{}_average_power,station{}_average_usage,station{}_average_sleep_time",
-                i, i, i
-            ))
-            .as_str();
+        for ((name, _), stats) in zip(self.metrics(), spread.metrics.iter()) {
+            msg += &format!(
+                "{:<30} | {} | {} | {} | {} | {} | {}\n",
+                name,
+                Self::pad(format!("{:.4}", stats.mean), 10),
+                Self::pad(format!("{:.4}", stats.std_dev), 10),
+                Self::pad(format!("{:.4}", stats.min), 10),
+                Self::pad(format!("{:.4}", stats.max), 10),
+                Self::pad(format!("{:.4}", stats.ci_low), 10),
+                Self::pad(format!("{:.4}", stats.ci_high), 10),
+            );
         }
         msg
     }
 
+    pub fn get_csv_header(&self) -> String {
+        let metrics = self.metrics();
+        let mut names: Vec<String> = metrics.iter().map(|(name, _)| name.clone()).collect();
+        if self.spread.is_some() {
+            for (name, _) in metrics.iter() {
+                for suffix in ["std", "min", "max", "ci_low", "ci_high"] {
+                    names.push(format!("{}_{}", name, suffix));
+                }
+            }
+        }
+        names.join(",")
+    }
+
     pub fn get_csv(&self) -> String {
-        let mut data = format!(
-            "{},{},{},{},{}",
-            self.total_users,
-            self.dropped_users,
-            self.average_usage,
-            self.average_power,
-            self.average_drop_rate
-        );
-        for station in self.stations.iter() {
-            data += &format!(
-                ",{},{},{}",
-                station.average_power, station.average_usage, station.average_sleep_time
-            );
+        let mut values: Vec<String> = self
+            .metrics()
+            .iter()
+            .map(|(_, value)| value.to_string())
+            .collect();
+        if let Some(spread) = &self.spread {
+            for stats in spread.metrics.iter() {
+                for value in [
+                    stats.std_dev,
+                    stats.min,
+                    stats.max,
+                    stats.ci_low,
+                    stats.ci_high,
+                ] {
+                    values.push(value.to_string());
+                }
+            }
         }
-        data
+        values.join(",")
     }
 }
@@ -0,0 +1,524 @@
+use crate::{
+    basestation::{BaseStation, BaseStationState},
+    config::{Config, SleepControllerKind},
+    sim_container::SimState,
+};
+
+/// Change of station power state requested by sleep controller
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerDecision {
+    /// Wake up sleeping station. If `offload_from` is given, half of users
+    /// from that station are moved to the woken up one.
+    WakeUp {
+        station: usize,
+        offload_from: Option<usize>,
+    },
+    /// Put active station to sleep and distribute its users between other active stations
+    ShutDown { station: usize },
+    /// Move sleeping station into the next deeper sleep level
+    Demote { station: usize },
+}
+
+/// Energy-saving strategy deciding which stations should be powered up or down.
+/// It is asked for decisions after every processed event.
+pub trait SleepController {
+    fn decide(
+        &mut self,
+        sim_state: &SimState,
+        stations: &[BaseStation],
+        cfg: &Config,
+    ) -> Vec<PowerDecision>;
+
+    /// Demotes sleeping stations, which stayed in their sleep level for idle_time of the next level.
+    /// It is asked for decisions together with `decide`, after every processed event
+    fn demote(&mut self, sim_state: &SimState, stations: &[BaseStation]) -> Vec<PowerDecision> {
+        stations
+            .iter()
+            .filter(|s| match s.state {
+                BaseStationState::Sleep(level) => s
+                    .params
+                    .sleep_levels
+                    .get(level + 1)
+                    .is_some_and(|next| sim_state.time - s.sleep_since >= next.idle_time * 1000),
+                _ => false,
+            })
+            .map(|s| PowerDecision::Demote { station: s.id })
+            .collect()
+    }
+}
+
+pub fn create_controller(kind: &SleepControllerKind, cfg: &Config) -> Box<dyn SleepController> {
+    match kind {
+        SleepControllerKind::Threshold => Box::new(ThresholdController),
+        SleepControllerKind::Hysteresis { hold_time } => Box::new(HysteresisController {
+            hold_time: hold_time * 1000,
+            above_since: vec![None; cfg.stations_count],
+            below_since: vec![None; cfg.stations_count],
+        }),
+        SleepControllerKind::MinAwake { count } => Box::new(MinAwakeController { count: *count }),
+    }
+}
+
+/// Returns indexes and remaining capacity of all active neighbours of station with given index,
+/// if they can take over all users of that station.
+/// There always must be at least single active station, so besides shutdown candidate
+/// there must be at least 2 other active stations.
+pub fn offload_targets(
+    idx: usize,
+    stations: &[BaseStation],
+    cfg: &Config,
+) -> Option<Vec<(usize, usize)>> {
+    let mut active_capacity: usize = 0;
+    let active_capacity_list: Vec<(usize, usize)> = stations
+        .iter()
+        .enumerate()
+        .filter(|(i, s)| s.is_active() && *i != idx && cfg.topology.is_neighbour(idx, *i))
+        .map(|(i, s)| {
+            let capacity = s.get_free_resources();
+            active_capacity += capacity;
+            (i, capacity)
+        })
+        .collect();
+    if active_capacity_list.len() < 2 || stations[idx].get_usage_raw() >= active_capacity {
+        return None;
+    }
+    Some(active_capacity_list)
+}
+
+/// Plans redirection of users occupying given numbers of blocks to stations returned by
+/// `offload_targets`. Users are taken from the end of list and split proportionally to remaining
+/// capacity of stations, and users that do not fit into their share are placed
+/// into the first station with enough space. User can be placed only into station,
+/// for which `allowed` called with index of user and index of station returns true.
+/// Returns index of target station for every user, or None if some user does not fit anywhere.
+pub fn plan_offload(
+    blocks: &[usize],
+    targets: &[(usize, usize)],
+    allowed: impl Fn(usize, usize) -> bool,
+) -> Option<Vec<usize>> {
+    let mut free: Vec<usize> = targets.iter().map(|(_, capacity)| *capacity).collect();
+    let total_capacity: usize = free.iter().sum();
+    let total_blocks: usize = blocks.iter().sum();
+    let mut plan = vec![0; blocks.len()];
+    let mut next = blocks.len();
+    for (target, (idx, capacity)) in targets.iter().enumerate() {
+        // all remaining users are offered to the last station
+        let share = if target + 1 == targets.len() {
+            usize::MAX
+        } else {
+            capacity * total_blocks / total_capacity
+        };
+        let mut taken = 0;
+        while next > 0
+            && taken + blocks[next - 1] <= share
+            && free[target] >= blocks[next - 1]
+            && allowed(next - 1, *idx)
+        {
+            next -= 1;
+            taken += blocks[next];
+            free[target] -= blocks[next];
+            plan[next] = target;
+        }
+    }
+    for (user, user_blocks) in blocks[..next].iter().enumerate() {
+        let target = (0..targets.len())
+            .find(|target| free[*target] >= *user_blocks && allowed(user, targets[*target].0))?;
+        free[target] -= user_blocks;
+        plan[user] = target;
+    }
+    Some(plan.into_iter().map(|target| targets[target].0).collect())
+}
+
+fn is_awake(station: &BaseStation) -> bool {
+    matches!(
+        station.state,
+        BaseStationState::Active | BaseStationState::PowerUp(_)
+    )
+}
+
+/// Returns sleep level of sleeping station
+fn sleep_level(station: &BaseStation) -> Option<usize> {
+    match station.state {
+        BaseStationState::Sleep(level) => Some(level),
+        _ => None,
+    }
+}
+
+/// Wakes up sleeping neighbour of station, which exceeds wakeup_threshold.
+/// Neighbour in the shallowest sleep level is chosen, first one of them if there are more.
+/// Otherwise puts to sleep first active station below sleep_threshold,
+/// if its users fit into its active neighbours.
+pub struct ThresholdController;
+
+impl ThresholdController {
+    fn wakeup(heavy_load_idx: usize, stations: &[BaseStation], cfg: &Config) -> Vec<PowerDecision> {
+        // Assumption -> wakeup station is empty or has very little users registered
+        // so there is need for only one redirection
+        stations
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| cfg.topology.is_neighbour(heavy_load_idx, *i))
+            .filter_map(|(i, x)| sleep_level(x).map(|level| (i, level)))
+            .min_by_key(|(_, level)| *level)
+            .map(|(idx, _)| PowerDecision::WakeUp {
+                station: idx,
+                offload_from: Some(heavy_load_idx),
+            })
+            .into_iter()
+            .collect()
+    }
+
+    fn shutdown(idx: usize, stations: &[BaseStation], cfg: &Config) -> Vec<PowerDecision> {
+        match offload_targets(idx, stations, cfg) {
+            Some(_) => vec![PowerDecision::ShutDown { station: idx }],
+            None => Vec::new(),
+        }
+    }
+}
+
+impl SleepController for ThresholdController {
+    fn decide(
+        &mut self,
+        _sim_state: &SimState,
+        stations: &[BaseStation],
+        cfg: &Config,
+    ) -> Vec<PowerDecision> {
+        let heavy_load = stations
+            .iter()
+            .position(|x| x.get_usage() >= x.params.wakeup_threshold);
+        if let Some(idx) = heavy_load {
+            return Self::wakeup(idx, stations, cfg);
+        }
+        // Find station with usage below sleep_threshold
+        let shutdown_idx = stations
+            .iter()
+            .position(|s| s.is_active() && s.get_usage() <= s.params.sleep_threshold);
+        match shutdown_idx {
+            Some(idx) => Self::shutdown(idx, stations, cfg),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Threshold rule, where station must stay above wakeup_threshold or below sleep_threshold
+/// for at least hold_time before any decision is made.
+pub struct HysteresisController {
+    hold_time: u64, // [us]
+    above_since: Vec<Option<u64>>,
+    below_since: Vec<Option<u64>>,
+}
+
+impl SleepController for HysteresisController {
+    fn decide(
+        &mut self,
+        sim_state: &SimState,
+        stations: &[BaseStation],
+        cfg: &Config,
+    ) -> Vec<PowerDecision> {
+        let time = sim_state.time;
+        for (i, station) in stations.iter().enumerate() {
+            let usage = station.get_usage();
+            self.above_since[i] = if usage >= station.params.wakeup_threshold {
+                self.above_since[i].or(Some(time))
+            } else {
+                None
+            };
+            self.below_since[i] = if station.is_active() && usage <= station.params.sleep_threshold
+            {
+                self.below_since[i].or(Some(time))
+            } else {
+                None
+            };
+        }
+        let hold_time = self.hold_time;
+        let held = |since: &Option<u64>| since.is_some_and(|t| time - t >= hold_time);
+
+        if let Some(idx) = self.above_since.iter().position(held) {
+            let decisions = ThresholdController::wakeup(idx, stations, cfg);
+            if !decisions.is_empty() {
+                self.above_since[idx] = None;
+            }
+            return decisions;
+        }
+        match self.below_since.iter().position(held) {
+            Some(idx) => {
+                let decisions = ThresholdController::shutdown(idx, stations, cfg);
+                if !decisions.is_empty() {
+                    self.below_since[idx] = None;
+                }
+                decisions
+            }
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Threshold rule, which never lets number of awake stations drop below given count.
+/// If there are less awake stations, sleeping ones in the shallowest sleep levels are woken up
+/// without redirecting any users.
+pub struct MinAwakeController {
+    count: usize,
+}
+
+impl SleepController for MinAwakeController {
+    fn decide(
+        &mut self,
+        sim_state: &SimState,
+        stations: &[BaseStation],
+        cfg: &Config,
+    ) -> Vec<PowerDecision> {
+        let awake = stations.iter().filter(|s| is_awake(s)).count();
+        if awake < self.count {
+            let mut sleeping: Vec<(usize, usize)> = stations
+                .iter()
+                .enumerate()
+                .filter_map(|(i, s)| sleep_level(s).map(|level| (i, level)))
+                .collect();
+            sleeping.sort_by_key(|(_, level)| *level);
+            return sleeping
+                .into_iter()
+                .take(self.count - awake)
+                .map(|(idx, _)| PowerDecision::WakeUp {
+                    station: idx,
+                    offload_from: None,
+                })
+                .collect();
+        }
+        let decisions = ThresholdController.decide(sim_state, stations, cfg);
+        let shutdown = decisions
+            .iter()
+            .any(|d| matches!(d, PowerDecision::ShutDown { .. }));
+        if shutdown && awake <= self.count {
+            return Vec::new();
+        }
+        decisions
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{create_controller, offload_targets, plan_offload, PowerDecision};
+    use crate::{
+        basestation::{BaseStation, BaseStationState},
+        config::{Config, SleepControllerKind, SleepLevel, Topology},
+        sim_container::SimState,
+        user::User,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn create_stations(cfg: &Config, usage: &[usize]) -> Vec<BaseStation> {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut stations = Vec::new();
+        for (i, count) in usage.iter().enumerate() {
+            let mut station = BaseStation::new(i, cfg, 1.0, &mut rng);
+            for id in 0..*count {
+                station.force_add_user(User::test(id, 10));
+            }
+            stations.push(station);
+        }
+        stations
+    }
+
+    #[test]
+    fn threshold() {
+        let cfg = Config {
+            stations_count: 3,
+            resources_count: 10,
+            ..Default::default()
+        };
+        let sim_state = SimState::new(&cfg);
+        let mut controller = create_controller(&SleepControllerKind::Threshold, &cfg);
+        let mut stations = create_stations(&cfg, &[10, 0, 0]);
+        stations[1].state = BaseStationState::Sleep(0);
+        assert_eq!(
+            controller.decide(&sim_state, &stations, &cfg),
+            vec![PowerDecision::WakeUp {
+                station: 1,
+                offload_from: Some(0)
+            }]
+        );
+        // no sleeping station to wake up
+        stations[1].state = BaseStationState::PowerUp(10);
+        assert!(controller.decide(&sim_state, &stations, &cfg).is_empty());
+
+        // shutdown of station 0 requires 2 other active stations
+        let mut stations = create_stations(&cfg, &[1, 5, 5]);
+        assert_eq!(
+            controller.decide(&sim_state, &stations, &cfg),
+            vec![PowerDecision::ShutDown { station: 0 }]
+        );
+        stations[1].state = BaseStationState::Sleep(0);
+        assert!(controller.decide(&sim_state, &stations, &cfg).is_empty());
+    }
+
+    #[test]
+    fn topology() {
+        // stations in line 0 - 1 - 2 - 3
+        let mut topology = Topology::Adjacency {
+            neighbours: vec![vec![1], vec![2], vec![3], vec![]],
+        };
+        topology.prepare();
+        let cfg = Config {
+            stations_count: 4,
+            resources_count: 10,
+            topology,
+            ..Default::default()
+        };
+        let sim_state = SimState::new(&cfg);
+        let mut controller = create_controller(&SleepControllerKind::Threshold, &cfg);
+        let mut stations = create_stations(&cfg, &[5, 5, 9, 0]);
+        stations[0].state = BaseStationState::Sleep(0);
+        stations[3].state = BaseStationState::Sleep(0);
+        // station 0 is not neighbour of overloaded station 2
+        assert_eq!(
+            controller.decide(&sim_state, &stations, &cfg),
+            vec![PowerDecision::WakeUp {
+                station: 3,
+                offload_from: Some(2)
+            }]
+        );
+        // station 0 has only one neighbour to take over its users
+        let stations = create_stations(&cfg, &[0, 1, 5, 5]);
+        assert_eq!(offload_targets(0, &stations, &cfg), None);
+        assert_eq!(
+            offload_targets(2, &stations, &cfg),
+            Some(vec![(1, 9), (3, 5)])
+        );
+    }
+
+    #[test]
+    fn offload() {
+        let cfg = Config {
+            resources_count: 10,
+            ..Default::default()
+        };
+        let stations = create_stations(&cfg, &[1, 9, 9]);
+        assert_eq!(
+            offload_targets(0, &stations, &cfg),
+            Some(vec![(1, 1), (2, 1)])
+        );
+        let stations = create_stations(&cfg, &[2, 9, 9]);
+        assert_eq!(offload_targets(0, &stations, &cfg), None);
+
+        // share of station 1: 10 / 24 * 4 -> 1 block
+        let targets = [(1, 10), (2, 14)];
+        let any = |_, _| true;
+        assert_eq!(
+            plan_offload(&[1, 1, 1, 1], &targets, any),
+            Some(vec![2, 2, 2, 1])
+        );
+        assert_eq!(
+            plan_offload(&[2, 3], &[(1, 3), (2, 2)], any),
+            Some(vec![2, 1])
+        );
+        // wide user does not fit anywhere
+        assert_eq!(plan_offload(&[1, 6], &[(1, 5), (2, 5)], any), None);
+        // users are placed only into stations covering them
+        let covers = |user, idx| user != 3 || idx != 1;
+        assert_eq!(
+            plan_offload(&[1, 1, 1, 1], &targets, covers),
+            Some(vec![2, 2, 2, 2])
+        );
+        let covers = |user, idx| user != 0 || idx != 2;
+        assert_eq!(
+            plan_offload(&[1, 1, 1, 1], &targets, covers),
+            Some(vec![1, 2, 2, 1])
+        );
+        // user is not covered by any target
+        assert_eq!(plan_offload(&[1, 1], &targets, |user, _| user != 0), None);
+    }
+
+    #[test]
+    fn hysteresis() {
+        let cfg = Config {
+            stations_count: 3,
+            resources_count: 10,
+            ..Default::default()
+        };
+        let mut sim_state = SimState::new(&cfg);
+        let kind = SleepControllerKind::Hysteresis { hold_time: 10 };
+        let mut controller = create_controller(&kind, &cfg);
+        let stations = create_stations(&cfg, &[1, 5, 5]);
+        // below threshold for 0 and 9 ms
+        assert!(controller.decide(&sim_state, &stations, &cfg).is_empty());
+        sim_state.time = 9000;
+        assert!(controller.decide(&sim_state, &stations, &cfg).is_empty());
+        sim_state.time = 10000;
+        assert_eq!(
+            controller.decide(&sim_state, &stations, &cfg),
+            vec![PowerDecision::ShutDown { station: 0 }]
+        );
+    }
+
+    #[test]
+    fn sleep_levels() {
+        let level = |name: &str, idle_time| SleepLevel {
+            name: name.to_owned(),
+            power: 1.0,
+            wakeup_delay: 10,
+            idle_time,
+        };
+        let cfg = Config {
+            stations_count: 4,
+            resources_count: 10,
+            sleep_levels: vec![level("light", 0), level("deep", 100)],
+            ..Default::default()
+        };
+        let mut sim_state = SimState::new(&cfg);
+        let mut controller = create_controller(&SleepControllerKind::Threshold, &cfg);
+        let mut stations = create_stations(&cfg, &[10, 0, 0, 0]);
+        stations[1].state = BaseStationState::Sleep(1);
+        stations[2].state = BaseStationState::Sleep(0);
+        stations[3].state = BaseStationState::Sleep(0);
+        stations[3].sleep_since = 50_000;
+        // station in the shallowest level is woken up
+        assert_eq!(
+            controller.decide(&sim_state, &stations, &cfg),
+            vec![PowerDecision::WakeUp {
+                station: 2,
+                offload_from: Some(0)
+            }]
+        );
+        // station 1 is already in the deepest level
+        sim_state.time = 100_000;
+        assert_eq!(
+            controller.demote(&sim_state, &stations),
+            vec![PowerDecision::Demote { station: 2 }]
+        );
+        sim_state.time = 150_000;
+        assert_eq!(controller.demote(&sim_state, &stations).len(), 2);
+        assert!(stations[2].demote(sim_state.time));
+        assert!(!stations[2].demote(sim_state.time));
+        assert!(matches!(stations[2].state, BaseStationState::Sleep(1)));
+    }
+
+    #[test]
+    fn min_awake() {
+        let cfg = Config {
+            stations_count: 4,
+            resources_count: 10,
+            ..Default::default()
+        };
+        let sim_state = SimState::new(&cfg);
+        let kind = SleepControllerKind::MinAwake { count: 3 };
+        let mut controller = create_controller(&kind, &cfg);
+        let mut stations = create_stations(&cfg, &[1, 5, 5, 0]);
+        stations[1].state = BaseStationState::Sleep(0);
+        stations[3].state = BaseStationState::Sleep(0);
+        assert_eq!(
+            controller.decide(&sim_state, &stations, &cfg),
+            vec![PowerDecision::WakeUp {
+                station: 1,
+                offload_from: None
+            }]
+        );
+        // 3 awake stations - shutdown of station 0 is not allowed
+        stations[1].state = BaseStationState::Active;
+        assert!(controller.decide(&sim_state, &stations, &cfg).is_empty());
+        stations[3].state = BaseStationState::Active;
+        assert_eq!(
+            controller.decide(&sim_state, &stations, &cfg),
+            vec![PowerDecision::ShutDown { station: 0 }]
+        );
+    }
+}
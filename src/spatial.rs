@@ -0,0 +1,281 @@
+//! Spatial mode, where users appear at random positions of simulated area
+//! and attach to the nearest awake station covering their position.
+//!
+//! Arrivals are generated by single process for the whole network with rate equal to
+//! lambda multiplied by number of stations, so the total load is the same as without spatial mode.
+
+use rand::{rngs::StdRng, Rng};
+use rand_distr::{Distribution, Normal};
+
+use crate::{
+    arrival::{create_process, thinned_arrival, ArrivalProcess},
+    basestation::BaseStation,
+    config::{Config, LambdaProfile, Position, SpatialConfig, UserPlacement},
+    user::User,
+};
+
+/// Global source of users and handover checks in spatial mode
+#[derive(Debug)]
+pub struct SpatialSource {
+    arrival: Box<dyn ArrivalProcess>,
+    pub next_arrival: u64,
+    /// Time of the next handover check, u64::MAX if users do not move
+    pub next_handover: u64,
+}
+
+impl SpatialSource {
+    pub fn new(cfg: &Config, lambda: f64, rng: &mut StdRng) -> SpatialSource {
+        let next_handover = match &cfg.spatial {
+            Some(spatial) if spatial.is_mobile() => spatial.handover_interval * 1000,
+            _ => u64::MAX,
+        };
+        let mut source = SpatialSource {
+            arrival: create_process(&cfg.arrival_process),
+            next_arrival: 0,
+            next_handover,
+        };
+        source.advance(0, lambda, cfg, rng);
+        source
+    }
+
+    /// Returns timestamp of the earliest arrival or handover check
+    pub fn next_event(&self) -> u64 {
+        self.next_arrival.min(self.next_handover)
+    }
+
+    /// Generates timestamp of the first arrival after given time.
+    /// Given lambda of global step profile is used for single station, other profiles are handled by thinning
+    pub fn advance(&mut self, time: u64, lambda: f64, cfg: &Config, rng: &mut StdRng) {
+        let count = cfg.stations_count as f64;
+        let profile = &cfg.lambda_profile;
+        if *profile == LambdaProfile::Step {
+            self.next_arrival =
+                time.saturating_add(self.arrival.next_arrival(time, lambda * count, rng));
+            return;
+        }
+        let max_lambda = cfg.lambda * count * profile.max_coef(&cfg.lambda_coefs);
+        let lambda = |t: u64| cfg.lambda * count * profile.coef(t as f64, &cfg.lambda_coefs);
+        self.next_arrival = thinned_arrival(self.arrival.as_mut(), time, max_lambda, lambda, rng);
+    }
+}
+
+/// Draws position of new user according to configured placement
+pub fn spawn_position(spatial: &SpatialConfig, rng: &mut StdRng) -> Position {
+    let uniform = |rng: &mut StdRng| Position {
+        x: rng.gen::<f64>() * spatial.width,
+        y: rng.gen::<f64>() * spatial.height,
+    };
+    match &spatial.placement {
+        UserPlacement::Uniform => uniform(rng),
+        UserPlacement::Hotspots {
+            hotspots,
+            background,
+        } => {
+            let total = background + hotspots.iter().map(|h| h.weight).sum::<f64>();
+            let mut point = rng.gen::<f64>() * total;
+            let hotspot = hotspots.iter().find(|h| {
+                let found = point < h.weight;
+                point -= h.weight;
+                found
+            });
+            let Some(hotspot) = hotspot else {
+                return uniform(rng);
+            };
+            // users outside of area are moved to its border
+            let normal = |mean: f64, max: f64, rng: &mut StdRng| {
+                Normal::new(mean, hotspot.std_dev)
+                    .unwrap()
+                    .sample(rng)
+                    .clamp(0.0, max)
+            };
+            Position {
+                x: normal(hotspot.x, spatial.width, rng),
+                y: normal(hotspot.y, spatial.height, rng),
+            }
+        }
+        UserPlacement::DensityMap { cells, .. } => {
+            let total: f64 = cells.iter().flatten().sum();
+            let mut point = rng.gen::<f64>() * total;
+            let columns = cells[0].len();
+            let idx = cells
+                .iter()
+                .flatten()
+                .position(|w| {
+                    let found = point < *w;
+                    point -= w;
+                    found
+                })
+                .unwrap_or(cells.len() * columns - 1);
+            let cell_width = spatial.width / columns as f64;
+            let cell_height = spatial.height / cells.len() as f64;
+            Position {
+                x: ((idx % columns) as f64 + rng.gen::<f64>()) * cell_width,
+                y: ((idx / columns) as f64 + rng.gen::<f64>()) * cell_height,
+            }
+        }
+    }
+}
+
+/// Returns true if given position is in coverage area of station with given index
+pub fn is_covered(position: Position, idx: usize, cfg: &Config) -> bool {
+    match cfg.topology.coverage() {
+        Some((positions, radius)) => {
+            let p = positions[idx];
+            (p.x - position.x).hypot(p.y - position.y) <= radius
+        }
+        None => false,
+    }
+}
+
+/// Returns true if station with given index covers the last known position of given user.
+/// Users without position, i.e. outside of spatial mode, can be served by any station
+pub fn covers_user(user: &User, idx: usize, cfg: &Config) -> bool {
+    user.position()
+        .is_none_or(|position| is_covered(position, idx, cfg))
+}
+
+/// Returns index of the nearest active station, which covers given position
+pub fn nearest_station(
+    position: Position,
+    stations: &[BaseStation],
+    cfg: &Config,
+) -> Option<usize> {
+    nearest_where(position, stations, cfg, |s| s.is_active())
+}
+
+/// Returns index of the nearest active station, which covers given position
+/// and has enough free resource blocks for handed over user
+pub fn handover_target(
+    position: Position,
+    blocks: usize,
+    stations: &[BaseStation],
+    cfg: &Config,
+) -> Option<usize> {
+    nearest_where(position, stations, cfg, |s| {
+        s.is_active() && s.can_serve(blocks)
+    })
+}
+
+fn nearest_where(
+    position: Position,
+    stations: &[BaseStation],
+    cfg: &Config,
+    f: impl Fn(&BaseStation) -> bool,
+) -> Option<usize> {
+    let (positions, radius) = cfg.topology.coverage()?;
+    stations
+        .iter()
+        .filter(|s| f(s))
+        .map(|s| {
+            let p = positions[s.id];
+            (s.id, (p.x - position.x).hypot(p.y - position.y))
+        })
+        .filter(|(_, distance)| *distance <= radius)
+        .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+        .map(|(id, _)| id)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{nearest_station, spawn_position};
+    use crate::{
+        basestation::{BaseStation, BaseStationState},
+        config::{Config, Hotspot, Position, SpatialConfig, Topology, UserPlacement},
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::path::PathBuf;
+
+    #[test]
+    fn nearest() {
+        let position = |x: f64, y: f64| Position { x, y };
+        let cfg = Config {
+            stations_count: 3,
+            topology: Topology::Coverage {
+                positions: vec![
+                    position(0.0, 0.0),
+                    position(400.0, 0.0),
+                    position(1100.0, 0.0),
+                ],
+                radius: 300.0,
+                neighbours: Vec::new(),
+            },
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut stations: Vec<BaseStation> = (0..3)
+            .map(|i| BaseStation::new(i, &cfg, 1.0, &mut rng))
+            .collect();
+        assert_eq!(
+            nearest_station(position(150.0, 0.0), &stations, &cfg),
+            Some(0)
+        );
+        assert_eq!(
+            nearest_station(position(250.0, 0.0), &stations, &cfg),
+            Some(1)
+        );
+        // gap between coverage of stations 1 and 2
+        assert_eq!(nearest_station(position(750.0, 0.0), &stations, &cfg), None);
+        // sleeping station leaves coverage hole
+        stations[0].state = BaseStationState::Sleep(0);
+        assert_eq!(
+            nearest_station(position(150.0, 0.0), &stations, &cfg),
+            Some(1)
+        );
+        assert_eq!(nearest_station(position(0.0, 0.0), &stations, &cfg), None);
+    }
+
+    #[test]
+    fn placement() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let uniform = SpatialConfig {
+            width: 200.0,
+            height: 100.0,
+            ..Default::default()
+        };
+        for _ in 0..1000 {
+            let p = spawn_position(&uniform, &mut rng);
+            assert!((0.0..200.0).contains(&p.x) && (0.0..100.0).contains(&p.y));
+        }
+
+        let hotspots = SpatialConfig {
+            placement: UserPlacement::Hotspots {
+                hotspots: vec![Hotspot {
+                    x: 100.0,
+                    y: 900.0,
+                    std_dev: 10.0,
+                    weight: 3.0,
+                }],
+                background: 1.0,
+            },
+            ..Default::default()
+        };
+        let near = (0..10_000)
+            .map(|_| spawn_position(&hotspots, &mut rng))
+            .filter(|p| (p.x - 100.0).hypot(p.y - 900.0) < 50.0)
+            .count();
+        // 75 % of users from hotspot and small part of uniform background
+        assert!((7400..7800).contains(&near), "near: {}", near);
+
+        let mut density = SpatialConfig {
+            width: 300.0,
+            height: 200.0,
+            placement: UserPlacement::DensityMap {
+                path: PathBuf::from("tests/references/density_map.csv"),
+                cells: Vec::new(),
+            },
+            ..Default::default()
+        };
+        density.placement.load().unwrap();
+        // only cells (x: 100-200, y: 0-100) and (x: 200-300, y: 100-200) are populated, with weights 1:3
+        let mut counts = [0; 2];
+        for _ in 0..10_000 {
+            let p = spawn_position(&density, &mut rng);
+            match (p.x as usize / 100, p.y as usize / 100) {
+                (1, 0) => counts[0] += 1,
+                (2, 1) => counts[1] += 1,
+                cell => panic!("user in empty cell {:?}", cell),
+            }
+        }
+        assert!((2300..2700).contains(&counts[0]), "counts: {:?}", counts);
+    }
+}
@@ -0,0 +1,238 @@
+//! Statistical helpers used for aggregation of simulation results.
+
+/// Summary of single metric over all simulation iterations
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MetricStats {
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: f64,
+    pub max: f64,
+    pub ci_low: f64,
+    pub ci_high: f64,
+}
+
+impl MetricStats {
+    /// Calculates mean, sample standard deviation, min/max and Student-t confidence interval
+    /// at given level (e.g. 0.95) of given samples. At least 2 samples are required.
+    pub fn from_samples(samples: &[f64], level: f64) -> MetricStats {
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        let std_dev = variance.sqrt();
+        let t = student_t_quantile(1.0 - (1.0 - level) / 2.0, n - 1.0);
+        let half_width = t * std_dev / n.sqrt();
+        MetricStats {
+            mean,
+            std_dev,
+            min: samples.iter().copied().fold(f64::INFINITY, f64::min),
+            max: samples.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            ci_low: mean - half_width,
+            ci_high: mean + half_width,
+        }
+    }
+}
+
+/// Marginal Standard Error Rule (MSER-m) for detection of initial transient.
+/// Series is divided into batches of given size and truncation point minimizing
+/// `sum((y_i - mean)^2) / (n - d)^2` of remaining batches is searched in the first half of batches.
+/// Returns number of observations that should be discarded from the beginning of the series.
+pub fn mser_truncation(series: &[f64], batch: usize) -> usize {
+    let batches: Vec<f64> = series
+        .chunks_exact(batch)
+        .map(|c| c.iter().sum::<f64>() / batch as f64)
+        .collect();
+    let n = batches.len();
+    if n < 2 {
+        return 0;
+    }
+    // suffix sums allow O(1) evaluation of every truncation point
+    let mut sum = 0.0;
+    let mut sum_sq = 0.0;
+    let mut best = (f64::INFINITY, 0);
+    for d in (0..n).rev() {
+        sum += batches[d];
+        sum_sq += batches[d] * batches[d];
+        if d > n / 2 {
+            continue;
+        }
+        let count = (n - d) as f64;
+        let sq_dev = (sum_sq - sum * sum / count).max(0.0);
+        let mser = sq_dev / (count * count);
+        if mser <= best.0 {
+            best = (mser, d);
+        }
+    }
+    best.1 * batch
+}
+
+/// Natural logarithm of gamma function (Lanczos approximation)
+fn ln_gamma(x: f64) -> f64 {
+    const COEFS: [f64; 6] = [
+        76.18009172947146,
+        -86.50532032941677,
+        24.01409824083091,
+        -1.231739572450155,
+        0.1208650973866179e-2,
+        -0.5395239384953e-5,
+    ];
+    let mut y = x;
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let mut series = 1.000000000190015;
+    for c in COEFS {
+        y += 1.0;
+        series += c / y;
+    }
+    -tmp + (2.5066282746310005 * series / x).ln()
+}
+
+/// Continued fraction for incomplete beta function (modified Lentz's method)
+fn beta_cf(a: f64, b: f64, x: f64) -> f64 {
+    const MAX_ITER: usize = 300;
+    const EPS: f64 = 1e-14;
+    const FPMIN: f64 = 1e-300;
+    let qab = a + b;
+    let qap = a + 1.0;
+    let qam = a - 1.0;
+    let mut c = 1.0;
+    let mut d = 1.0 - qab * x / qap;
+    if d.abs() < FPMIN {
+        d = FPMIN;
+    }
+    d = 1.0 / d;
+    let mut h = d;
+    for m in 1..=MAX_ITER {
+        let m = m as f64;
+        let m2 = 2.0 * m;
+        let aa = m * (b - m) * x / ((qam + m2) * (a + m2));
+        d = 1.0 + aa * d;
+        if d.abs() < FPMIN {
+            d = FPMIN;
+        }
+        c = 1.0 + aa / c;
+        if c.abs() < FPMIN {
+            c = FPMIN;
+        }
+        d = 1.0 / d;
+        h *= d * c;
+        let aa = -(a + m) * (qab + m) * x / ((a + m2) * (qap + m2));
+        d = 1.0 + aa * d;
+        if d.abs() < FPMIN {
+            d = FPMIN;
+        }
+        c = 1.0 + aa / c;
+        if c.abs() < FPMIN {
+            c = FPMIN;
+        }
+        d = 1.0 / d;
+        let del = d * c;
+        h *= del;
+        if (del - 1.0).abs() < EPS {
+            break;
+        }
+    }
+    h
+}
+
+/// Regularized incomplete beta function I_x(a, b)
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_cf(a, b, x) / a
+    } else {
+        1.0 - front * beta_cf(b, a, 1.0 - x) / b
+    }
+}
+
+/// Cumulative distribution function of Student's t-distribution
+pub fn student_t_cdf(t: f64, df: f64) -> f64 {
+    let tail = 0.5 * incomplete_beta(df / 2.0, 0.5, df / (df + t * t));
+    if t >= 0.0 {
+        1.0 - tail
+    } else {
+        tail
+    }
+}
+
+/// Returns t such that P(T <= t) = p for Student's t-distribution with df degrees of freedom
+pub fn student_t_quantile(p: f64, df: f64) -> f64 {
+    if p == 0.5 {
+        return 0.0;
+    }
+    if p < 0.5 {
+        return -student_t_quantile(1.0 - p, df);
+    }
+    // find upper bound and bisect
+    let mut low = 0.0;
+    let mut high = 1.0;
+    while student_t_cdf(high, df) < p {
+        low = high;
+        high *= 2.0;
+    }
+    for _ in 0..200 {
+        let mid = 0.5 * (low + high);
+        if student_t_cdf(mid, df) < p {
+            low = mid;
+        } else {
+            high = mid;
+        }
+        if high - low < 1e-12 {
+            break;
+        }
+    }
+    0.5 * (low + high)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{mser_truncation, student_t_quantile, MetricStats};
+
+    #[test]
+    fn t_quantile() {
+        // reference values from statistical tables
+        for (p, df, t) in [
+            (0.975, 1.0, 12.7062),
+            (0.975, 4.0, 2.7764),
+            (0.975, 10.0, 2.2281),
+            (0.95, 30.0, 1.6973),
+            (0.995, 9.0, 3.2498),
+        ] {
+            let res = student_t_quantile(p, df);
+            assert!((res - t).abs() < 1e-3, "p: {} df: {} t: {}", p, df, res);
+        }
+        assert!((student_t_quantile(0.025, 10.0) + 2.2281).abs() < 1e-3);
+    }
+
+    #[test]
+    fn metric_stats() {
+        let stats = MetricStats::from_samples(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0], 0.95);
+        assert_eq!(stats.mean, 5.0);
+        assert!((stats.std_dev - 2.13809).abs() < 1e-5);
+        assert_eq!(stats.min, 2.0);
+        assert_eq!(stats.max, 9.0);
+        // t(0.975, 7) = 2.36462
+        let half_width = 2.36462 * stats.std_dev / 8.0_f64.sqrt();
+        assert!((stats.ci_low - (5.0 - half_width)).abs() < 1e-4);
+        assert!((stats.ci_high - (5.0 + half_width)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn mser() {
+        // ramp-up of 50 observations followed by oscillation around 10
+        let mut series: Vec<f64> = (0..50).map(|i| i as f64 / 5.0).collect();
+        series.extend((0..450).map(|i| 10.0 + if i % 2 == 0 { 0.5 } else { -0.5 }));
+        let d = mser_truncation(&series, 5);
+        assert_eq!(d % 5, 0);
+        assert!((45..=60).contains(&d), "truncation: {}", d);
+        // stationary series needs no truncation
+        assert_eq!(mser_truncation(&[1.0; 100], 5), 0);
+        assert_eq!(mser_truncation(&[1.0; 4], 5), 0);
+    }
+}
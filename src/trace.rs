@@ -0,0 +1,177 @@
+//! Recorded arrivals replayed instead of randomly generated users.
+//!
+//! Trace is a CSV file with `timestamp,station,holding_time[,blocks]` lines
+//! or JSON lines file with objects containing the same fields.
+//! Timestamp is given in seconds from the beginning of simulation and holding time in miliseconds.
+
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::{config::read_file, error::CassidyError, user::User};
+
+/// Single recorded arrival of user
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TraceRecord {
+    pub time: u64, // [us]
+    pub station: usize,
+    pub holding_time: u64, // [us]
+    pub blocks: usize,
+}
+
+impl TraceRecord {
+    pub fn user(&self, id: usize) -> User {
+        User {
+            id,
+            start: self.time,
+            end: self.time + self.holding_time,
+            blocks: self.blocks,
+            class: None,
+            movement: None,
+            priority: 0,
+        }
+    }
+}
+
+/// Record as written in trace file
+#[derive(Debug, Deserialize)]
+struct RawRecord {
+    timestamp: f64,    // [s]
+    station: usize,    // station id
+    holding_time: f64, // [ms]
+    #[serde(default = "default_blocks")]
+    blocks: usize,
+}
+
+fn default_blocks() -> usize {
+    1
+}
+
+impl RawRecord {
+    fn from_csv(line: &str) -> Option<RawRecord> {
+        let values: Vec<&str> = line.split(',').map(|v| v.trim()).collect();
+        let (timestamp, station, holding_time, blocks) = match values[..] {
+            [t, s, h] => (t, s, h, "1"),
+            [t, s, h, b] => (t, s, h, b),
+            _ => return None,
+        };
+        Some(RawRecord {
+            timestamp: timestamp.parse().ok()?,
+            station: station.parse().ok()?,
+            holding_time: holding_time.parse().ok()?,
+            blocks: blocks.parse().ok()?,
+        })
+    }
+}
+
+/// Reads trace from given file, sorted by arrival time.
+/// Files with `.jsonl`, `.ndjson` or `.json` extension are read as JSON lines, other files as CSV.
+/// Every record must belong to one of `stations_count` stations.
+pub fn load_trace(path: &Path, stations_count: usize) -> Result<Vec<TraceRecord>, CassidyError> {
+    let data = read_file(path)?;
+    let json = matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("jsonl" | "ndjson" | "json")
+    );
+    let invalid = |line: usize, msg: String| {
+        CassidyError::InvalidConfig(format!(
+            "trace {} line {}: {}",
+            path.display(),
+            line + 1,
+            msg
+        ))
+    };
+    let mut records = Vec::new();
+    for (idx, line) in data.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let raw = if json {
+            serde_json::from_str::<RawRecord>(line).map_err(|e| invalid(idx, e.to_string()))?
+        } else {
+            match RawRecord::from_csv(line) {
+                Some(raw) => raw,
+                // optional header
+                None if records.is_empty() && line.starts_with(|c: char| c.is_alphabetic()) => {
+                    continue
+                }
+                None => {
+                    return Err(invalid(
+                        idx,
+                        "expected values in format timestamp,station,holding_time[,blocks]"
+                            .to_owned(),
+                    ))
+                }
+            }
+        };
+        if raw.timestamp < 0.0 || raw.holding_time < 0.0 {
+            return Err(invalid(
+                idx,
+                "timestamp and holding_time must not be negative".to_owned(),
+            ));
+        }
+        if raw.station >= stations_count {
+            return Err(invalid(
+                idx,
+                format!(
+                    "station {} does not exist, stations_count is {}",
+                    raw.station, stations_count
+                ),
+            ));
+        }
+        if raw.blocks == 0 {
+            return Err(invalid(idx, "blocks must be greater than 0".to_owned()));
+        }
+        records.push(TraceRecord {
+            time: (raw.timestamp * 1_000_000.0) as u64,
+            station: raw.station,
+            holding_time: (raw.holding_time * 1000.0) as u64,
+            blocks: raw.blocks,
+        });
+    }
+    // stable sort keeps order of users arriving at the same time
+    records.sort_by_key(|r| r.time);
+    Ok(records)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{load_trace, TraceRecord};
+    use crate::error::CassidyError;
+    use std::path::Path;
+
+    #[test]
+    fn load() {
+        let expected = vec![
+            TraceRecord {
+                time: 500_000,
+                station: 1,
+                holding_time: 2_000_000,
+                blocks: 1,
+            },
+            TraceRecord {
+                time: 1_000_000,
+                station: 0,
+                holding_time: 10_000_000,
+                blocks: 3,
+            },
+            TraceRecord {
+                time: 1_000_000,
+                station: 2,
+                holding_time: 1_500,
+                blocks: 1,
+            },
+        ];
+        let csv = load_trace(Path::new("tests/references/trace.csv"), 3).unwrap();
+        assert_eq!(csv, expected);
+        let json = load_trace(Path::new("tests/references/trace.jsonl"), 3).unwrap();
+        assert_eq!(json, expected);
+
+        let err = load_trace(Path::new("tests/references/trace.csv"), 2).unwrap_err();
+        assert!(matches!(err, CassidyError::InvalidConfig(_)));
+        assert!(err.to_string().contains("line 5"), "{}", err);
+        let err = load_trace(Path::new("tests/references/missing.csv"), 3).unwrap_err();
+        assert!(matches!(err, CassidyError::Io { .. }));
+    }
+}
@@ -1,31 +1,213 @@
 use std::fmt::Display;
 
-use crate::config::Config;
+use crate::{
+    config::{BlocksDistribution, Config, Position, ServiceClass, ServiceTime},
+    mobility::Movement,
+};
 use rand::{self, rngs::StdRng, Rng};
+use rand_distr::{Distribution, Exp, LogNormal, Pareto};
 
 #[derive(Debug)]
 pub struct User {
     pub id: usize,
     pub start: u64,
     pub end: u64,
+    pub blocks: usize,              // number of occupied resource blocks
+    pub class: Option<usize>,       // index of service class, if classes are configured
+    pub movement: Option<Movement>, // position of user in spatial mode, with its leg if user moves
+    pub priority: usize,            // priority level, or priority of service class
 }
 
 impl User {
+    /// Creates user with random process time.
+    /// If service classes are configured, class and number of blocks are also drawn,
+    /// otherwise user occupies single block.
+    /// If priority levels are configured, priority is drawn last.
     pub fn new(id: usize, curr_time: u64, generator: &mut StdRng, cfg: &Config) -> User {
-        // convert process_time from miliseconds to microseconds
-        let delay: u64 =
-            generator.gen_range((cfg.process_time_min * 1000)..=(cfg.process_time_max * 1000));
+        let mut user = Self::new_class_user(id, curr_time, generator, cfg);
+        if let Some(priorities) = &cfg.priorities {
+            user.priority = Self::draw_priority(&priorities.shares, generator);
+        }
+        user
+    }
+
+    /// Returns position of user at the last update of its movement, None outside of spatial mode
+    pub fn position(&self) -> Option<Position> {
+        self.movement.map(|m| m.position)
+    }
+
+    fn new_class_user(id: usize, curr_time: u64, generator: &mut StdRng, cfg: &Config) -> User {
+        if cfg.service_classes.is_empty() {
+            let delay = Self::draw_service_time(
+                &cfg.service_time,
+                cfg.process_time_min,
+                cfg.process_time_max,
+                generator,
+            );
+            return User {
+                id,
+                start: curr_time,
+                end: curr_time + delay,
+                blocks: 1,
+                class: None,
+                movement: None,
+                priority: 0,
+            };
+        }
+        let class_idx = Self::draw_class(&cfg.service_classes, generator);
+        let class = &cfg.service_classes[class_idx];
+        let delay = Self::draw_service_time(
+            class.service_time.as_ref().unwrap_or(&cfg.service_time),
+            class.process_time_min.unwrap_or(cfg.process_time_min),
+            class.process_time_max.unwrap_or(cfg.process_time_max),
+            generator,
+        );
         User {
             id,
             start: curr_time,
             end: curr_time + delay,
+            blocks: Self::draw_blocks(&class.blocks, generator),
+            class: Some(class_idx),
+            movement: None,
+            priority: class.priority,
+        }
+    }
+
+    /// Returns holding time in microseconds. `min` and `max` [ms] are used only by uniform distribution
+    pub(crate) fn draw_service_time(
+        distribution: &ServiceTime,
+        min: u64,
+        max: u64,
+        generator: &mut StdRng,
+    ) -> u64 {
+        let time = match distribution {
+            // convert process_time from miliseconds to microseconds
+            ServiceTime::Uniform => return generator.gen_range((min * 1000)..=(max * 1000)),
+            ServiceTime::Exponential { mean } => Exp::new(1.0 / mean).unwrap().sample(generator),
+            ServiceTime::LogNormal { mean, std_dev } => {
+                let sigma2 = (1.0 + (std_dev / mean).powi(2)).ln();
+                let mu = mean.ln() - sigma2 / 2.0;
+                LogNormal::new(mu, sigma2.sqrt()).unwrap().sample(generator)
+            }
+            ServiceTime::Pareto { scale, shape } => {
+                Pareto::new(*scale, *shape).unwrap().sample(generator)
+            }
+            ServiceTime::Deterministic { time } => *time,
+            ServiceTime::Empirical { bins, .. } => {
+                let total: f64 = bins.iter().map(|b| b.weight).sum();
+                let mut point = generator.gen::<f64>() * total;
+                let bin = bins
+                    .iter()
+                    .find(|b| {
+                        let found = point < b.weight;
+                        point -= b.weight;
+                        found
+                    })
+                    .unwrap_or(&bins[bins.len() - 1]);
+                generator.gen_range(bin.lower..=bin.upper)
+            }
+        };
+        (time * 1000.0) as u64
+    }
+
+    fn draw_class(classes: &[ServiceClass], generator: &mut StdRng) -> usize {
+        let total: f64 = classes.iter().map(|c| c.share).sum();
+        let mut point = generator.gen::<f64>() * total;
+        for (idx, class) in classes.iter().enumerate() {
+            if point < class.share {
+                return idx;
+            }
+            point -= class.share;
+        }
+        classes.len() - 1
+    }
+
+    fn draw_priority(shares: &[f64], generator: &mut StdRng) -> usize {
+        let total: f64 = shares.iter().sum();
+        let mut point = generator.gen::<f64>() * total;
+        for (level, share) in shares.iter().enumerate() {
+            if point < *share {
+                return level;
+            }
+            point -= share;
+        }
+        shares.len() - 1
+    }
+
+    fn draw_blocks(distribution: &BlocksDistribution, generator: &mut StdRng) -> usize {
+        match distribution {
+            BlocksDistribution::Constant { count } => *count,
+            BlocksDistribution::Uniform { min, max } => generator.gen_range(*min..=*max),
+            BlocksDistribution::Weighted { points } => {
+                let total: f64 = points.iter().map(|p| p.weight).sum();
+                let mut point = generator.gen::<f64>() * total;
+                for p in points.iter() {
+                    if point < p.weight {
+                        return p.blocks;
+                    }
+                    point -= p.weight;
+                }
+                points[points.len() - 1].blocks
+            }
         }
     }
 }
 
+#[cfg(test)]
+impl User {
+    /// Creates single block user without class and priority, used as fixture in tests
+    pub(crate) fn test(id: usize, end: u64) -> User {
+        User {
+            id,
+            start: 0,
+            end,
+            blocks: 1,
+            class: None,
+            movement: None,
+            priority: 0,
+        }
+    }
+
+    pub(crate) fn with_start(mut self, start: u64) -> User {
+        self.start = start;
+        self
+    }
+
+    pub(crate) fn with_blocks(mut self, blocks: usize) -> User {
+        self.blocks = blocks;
+        self
+    }
+
+    pub(crate) fn with_class(mut self, class: usize) -> User {
+        self.class = Some(class);
+        self
+    }
+
+    pub(crate) fn with_priority(mut self, priority: usize) -> User {
+        self.priority = priority;
+        self
+    }
+
+    /// Places user at given position of area, where it does not move
+    pub(crate) fn with_position(mut self, x: f64, y: f64) -> User {
+        use crate::config::SpatialConfig;
+        use rand::SeedableRng;
+        // static user does not draw legs of movement
+        let mut rng = StdRng::seed_from_u64(0);
+        let position = Position { x, y };
+        let movement = Movement::new(position, self.start, &SpatialConfig::default(), &mut rng);
+        self.movement = Some(movement);
+        self
+    }
+}
+
 impl Display for User {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "User id: {}, end time: {}", self.id, self.end,)
+        write!(f, "User id: {}, end time: {}", self.id, self.end,)?;
+        if let Some(class) = self.class {
+            write!(f, ", blocks: {}, class: {}", self.blocks, class)?;
+        }
+        Ok(())
     }
 }
 
@@ -34,8 +216,11 @@ mod test {
     use std::io::Write;
 
     use super::User;
-    use crate::config::Config;
+    use crate::config::{
+        BlocksDistribution, BlocksWeight, Config, PriorityConfig, ServiceClass, ServiceTime,
+    };
     use rand::{rngs::StdRng, SeedableRng};
+    use std::path::PathBuf;
 
     #[test]
     fn test_rng() {
@@ -55,4 +240,132 @@ mod test {
             );
         }
     }
+
+    #[test]
+    fn service_classes() {
+        let class = |name: &str, share, blocks| ServiceClass {
+            name: name.to_owned(),
+            share,
+            process_time_min: Some(100),
+            process_time_max: Some(200),
+            service_time: None,
+            blocks,
+            priority: 0,
+        };
+        let cfg = Config {
+            service_classes: vec![
+                class("voice", 3.0, BlocksDistribution::Constant { count: 1 }),
+                class("video", 1.0, BlocksDistribution::Uniform { min: 4, max: 8 }),
+                class(
+                    "data",
+                    1.0,
+                    BlocksDistribution::Weighted {
+                        points: vec![
+                            BlocksWeight {
+                                blocks: 2,
+                                weight: 1.0,
+                            },
+                            BlocksWeight {
+                                blocks: 10,
+                                weight: 1.0,
+                            },
+                        ],
+                    },
+                ),
+            ],
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut counts = [0; 3];
+        for id in 0..10000 {
+            let user = User::new(id, 0, &mut rng, &cfg);
+            let class = user.class.unwrap();
+            counts[class] += 1;
+            assert!(user.end >= 100_000 && user.end <= 200_000);
+            match class {
+                0 => assert_eq!(user.blocks, 1),
+                1 => assert!((4..=8).contains(&user.blocks)),
+                _ => assert!(user.blocks == 2 || user.blocks == 10),
+            }
+        }
+        // shares 3:1:1
+        assert!((5800..6200).contains(&counts[0]), "{:?}", counts);
+        assert!((1800..2200).contains(&counts[1]), "{:?}", counts);
+
+        // without classes every user occupies single block
+        let user = User::new(0, 0, &mut rng, &Config::default());
+        assert_eq!((user.blocks, user.class), (1, None));
+    }
+
+    #[test]
+    fn priorities() {
+        let cfg = Config {
+            priorities: Some(PriorityConfig {
+                shares: vec![6.0, 3.0, 1.0],
+                preemption: true,
+            }),
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut counts = [0; 3];
+        for id in 0..10000 {
+            counts[User::new(id, 0, &mut rng, &cfg).priority] += 1;
+        }
+        assert!((5800..6200).contains(&counts[0]), "{:?}", counts);
+        assert!((2800..3200).contains(&counts[1]), "{:?}", counts);
+    }
+
+    #[test]
+    fn service_time() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mean_of = |service_time: ServiceTime, rng: &mut StdRng| {
+            let cfg = Config {
+                service_time,
+                ..Default::default()
+            };
+            let n = 20000;
+            let total: u64 = (0..n).map(|id| User::new(id, 0, rng, &cfg).end).sum();
+            total as f64 / n as f64 / 1000.0
+        };
+        let mean = mean_of(ServiceTime::Exponential { mean: 5000.0 }, &mut rng);
+        assert!((mean - 5000.0).abs() < 150.0, "exponential mean: {}", mean);
+        let log_normal = ServiceTime::LogNormal {
+            mean: 5000.0,
+            std_dev: 2000.0,
+        };
+        let mean = mean_of(log_normal, &mut rng);
+        assert!((mean - 5000.0).abs() < 100.0, "log-normal mean: {}", mean);
+        // mean of Pareto distribution: shape * scale / (shape - 1)
+        let pareto = ServiceTime::Pareto {
+            scale: 1000.0,
+            shape: 3.0,
+        };
+        let mean = mean_of(pareto, &mut rng);
+        assert!((mean - 1500.0).abs() < 50.0, "pareto mean: {}", mean);
+        let mean = mean_of(ServiceTime::Deterministic { time: 1234.0 }, &mut rng);
+        assert_eq!(mean, 1234.0);
+
+        let mut empirical = ServiceTime::Empirical {
+            path: PathBuf::from("tests/references/service_time.csv"),
+            bins: Vec::new(),
+        };
+        empirical.load().unwrap();
+        let cfg = Config {
+            service_time: empirical,
+            ..Default::default()
+        };
+        let mut short = 0;
+        for id in 0..10000 {
+            let time = User::new(id, 0, &mut rng, &cfg).end;
+            assert!(
+                (1_000_000..=2_000_000).contains(&time)
+                    || (10_000_000..=20_000_000).contains(&time)
+            );
+            if time <= 2_000_000 {
+                short += 1;
+            }
+        }
+        // weights 3:1
+        assert!((7300..7700).contains(&short), "short: {}", short);
+    }
 }